@@ -1,5 +1,6 @@
 use webui_rs::webui;
 use webui_rs::Browser;
+use webui_rs::WebUiError;
 use webui_rs::Window;
 
 const HTML: &str = r#"
@@ -7,19 +8,26 @@ const HTML: &str = r#"
     <script src="/webui.js"></script>
     <button id="btn">Click me!</button>
     <button onclick="webui.call('add', 1, 2).then((res) => { console.log(res); })">Add 1 + 2</button>
+    <button onclick="webui.call('multiply', 3, 4).then((res) => { console.log(res); })">Multiply 3 * 4</button>
+    <button onclick="webui.call('divide', 1, 0).catch((err) => { console.error(err.code, err.message); })">Divide 1 / 0</button>
 </html>
 "#;
 
-fn main() {
+fn main() -> Result<(), WebUiError> {
     let win = Window::new();
 
-    win.show_browser(HTML, Browser::Firefox);
+    win.show_browser(HTML, Browser::Firefox)?;
 
-    win.bind("btn", |_| {
-        println!("Element clicked!");
+    win.on_connect(|event| {
+        println!("Client {} connected", event.client_id);
     });
 
-    win.bind("add", |event| {
+    // Handlers stay bound as long as their handles are alive
+    let _btn = win.bind("btn", |_| {
+        println!("Element clicked!");
+    })?;
+
+    let _add = win.bind("add", |event| {
         let count = event.get_count();
         if count < 2 {
             return;
@@ -31,7 +39,25 @@ fn main() {
         println!("{} + {} = {}", a, b, a + b);
 
         event.return_int(a + b);
-    });
+    })?;
+
+    // Arguments and return value are converted from the function signature
+    let _multiply = win.bind_typed("multiply", multiply)?;
+
+    // An `Err` rejects the promise returned by `webui.call()`
+    let _divide = win.bind_typed("divide", divide)?;
 
     webui::wait();
+
+    Ok(())
+}
+
+fn multiply(a: i64, b: i64) -> i64 {
+    println!("{} * {} = {}", a, b, a * b);
+    a * b
+}
+
+fn divide(a: i64, b: i64) -> Result<i64, String> {
+    a.checked_div(b)
+        .ok_or_else(|| format!("cannot divide {} by {}", a, b))
 }
@@ -0,0 +1,37 @@
+use webui_rs::webui;
+use webui_rs::WebUiError;
+use webui_rs::Window;
+
+const HTML: &str = r#"
+<html>
+    <script src="/webui.js"></script>
+    <button onclick="webui.call('add', 1, 2).then((res) => { console.log(res); })">Add 1 + 2</button>
+    <button onclick="webui.call('greet', 'World').then((res) => { console.log(res); })">Greet</button>
+</html>
+"#;
+
+#[webui::export]
+fn add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+#[webui::export(name = "greet")]
+fn hello(name: String) -> String {
+    format!("Hello, {}!", name)
+}
+
+fn main() -> Result<(), WebUiError> {
+    let win = Window::new();
+
+    // Bind every `#[webui::export]` function
+    webui::register_all(&win)?;
+
+    // The JavaScript side of the exports, e.g. to write next to the page
+    println!("{}", webui::js_bindings());
+
+    win.show(HTML)?;
+
+    webui::wait();
+
+    Ok(())
+}
@@ -1,8 +1,10 @@
-use webui_rs::{webui::wait, window::Window};
+use webui_rs::{webui::wait, window::Window, WebUiError};
 
-fn main() {
+fn main() -> Result<(), WebUiError> {
     let win = Window::new();
-    win.show("<html>Hello World!</html>");
+    win.show("<html>Hello World!</html>")?;
 
     wait();
+
+    Ok(())
 }
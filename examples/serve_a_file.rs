@@ -1,9 +1,11 @@
-use webui_rs::{webui::wait, window::Window};
+use webui_rs::{webui::wait, window::Window, WebUiError};
 
-fn main() {
+fn main() -> Result<(), WebUiError> {
     let win = Window::new();
 
-    win.show("examples/html/index.html");
+    win.show("examples/html/index.html")?;
 
     wait();
+
+    Ok(())
 }
@@ -0,0 +1,289 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::error::WebUiError;
+use crate::window::Window;
+use crate::Browser;
+use crate::Runtime;
+
+/// A setting of `WindowBuilder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Setting {
+    RootFolder,
+    Runtime,
+    Profile,
+    Proxy,
+    Port,
+    Kiosk,
+    Hidden,
+    Size,
+    Position,
+    Icon,
+    EventBlocking,
+}
+
+/// A setting that was rejected, either by validation or by webui.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingError {
+    pub setting: Setting,
+    pub reason: String,
+}
+
+impl fmt::Display for SettingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.setting, self.reason)
+    }
+}
+
+/// Returned by `WindowBuilder::show` when a setting or the show failed.
+/// The window is kept, with every other setting applied.
+#[derive(Debug)]
+pub struct BuildError {
+    pub window: Window,
+    pub failed: Vec<SettingError>,
+    pub shown: bool,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.shown {
+            write!(f, "window {} could not be shown", self.window.id)?;
+        } else {
+            write!(f, "window {} shown", self.window.id)?;
+        }
+
+        for (i, failed) in self.failed.iter().enumerate() {
+            let separator = if i == 0 { ", failed settings: " } else { ", " };
+            write!(f, "{}{}", separator, failed)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Configures a window before it is shown. Most webui settings are only read
+/// when the window is shown, `show` applies them in the right order first.
+#[derive(Debug, Default, Clone)]
+pub struct WindowBuilder {
+    root_folder: Option<String>,
+    runtime: Option<Runtime>,
+    profile: Option<(String, String)>,
+    proxy: Option<String>,
+    port: Option<usize>,
+    kiosk: Option<bool>,
+    hidden: Option<bool>,
+    size: Option<(u32, u32)>,
+    position: Option<(u32, u32)>,
+    icon: Option<(String, String)>,
+    event_blocking: Option<bool>,
+}
+
+impl WindowBuilder {
+    pub fn new() -> WindowBuilder {
+        WindowBuilder::default()
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn position(mut self, x: u32, y: u32) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    pub fn kiosk(mut self, status: bool) -> Self {
+        self.kiosk = Some(status);
+        self
+    }
+
+    pub fn hidden(mut self, status: bool) -> Self {
+        self.hidden = Some(status);
+        self
+    }
+
+    // Empty name and path select the default profile
+    pub fn profile(mut self, name: &str, path: &str) -> Self {
+        self.profile = Some((name.to_owned(), path.to_owned()));
+        self
+    }
+
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_owned());
+        self
+    }
+
+    pub fn port(mut self, port: usize) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn icon(mut self, icon: &str, kind: &str) -> Self {
+        self.icon = Some((icon.to_owned(), kind.to_owned()));
+        self
+    }
+
+    pub fn runtime(mut self, runtime: Runtime) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    pub fn root_folder(mut self, folder: &str) -> Self {
+        self.root_folder = Some(folder.to_owned());
+        self
+    }
+
+    pub fn event_blocking(mut self, status: bool) -> Self {
+        self.event_blocking = Some(status);
+        self
+    }
+
+    // Check the settings that can be checked without a window
+    pub fn validate(&self) -> Vec<SettingError> {
+        let mut failed = Vec::new();
+        let mut check = |setting: Setting, valid: bool, reason: &str| {
+            if !valid {
+                failed.push(SettingError {
+                    setting,
+                    reason: reason.to_owned(),
+                });
+            }
+        };
+
+        if let Some(folder) = &self.root_folder {
+            check(
+                Setting::RootFolder,
+                Path::new(folder).is_dir(),
+                "not a directory",
+            );
+            check(Setting::RootFolder, !has_nul(folder), "contains a NUL byte");
+        }
+        if let Some((name, path)) = &self.profile {
+            check(
+                Setting::Profile,
+                !has_nul(name) && !has_nul(path),
+                "contains a NUL byte",
+            );
+        }
+        if let Some(proxy) = &self.proxy {
+            check(Setting::Proxy, !has_nul(proxy), "contains a NUL byte");
+        }
+        if let Some(port) = self.port {
+            check(
+                Setting::Port,
+                (1..=u16::MAX as usize).contains(&port),
+                "not a valid port",
+            );
+        }
+        if let Some((width, height)) = self.size {
+            check(Setting::Size, width > 0 && height > 0, "empty window size");
+        }
+        if let Some((icon, kind)) = &self.icon {
+            check(Setting::Icon, !icon.is_empty(), "empty icon");
+            check(
+                Setting::Icon,
+                !has_nul(icon) && !has_nul(kind),
+                "contains a NUL byte",
+            );
+        }
+
+        failed
+    }
+
+    // Create the window, apply the settings and show it. Every valid setting
+    // is applied even when another one fails.
+    pub fn show(self, content: &str, browser: Browser) -> Result<Window, BuildError> {
+        let mut failed = self.validate();
+        let window = Window::new();
+
+        // Apply a setting unless its validation failed, recording webui errors
+        let mut apply = |setting: Setting, f: &dyn Fn() -> Result<(), WebUiError>| {
+            if failed.iter().any(|failed| failed.setting == setting) {
+                return;
+            }
+            if let Err(err) = f() {
+                failed.push(SettingError {
+                    setting,
+                    reason: err.to_string(),
+                });
+            }
+        };
+
+        // Server and profile settings first, then the browser window ones
+        if let Some(folder) = &self.root_folder {
+            apply(Setting::RootFolder, &|| window.set_root_folder(folder));
+        }
+        if let Some(runtime) = self.runtime {
+            window.set_runtime(runtime);
+        }
+        if let Some((name, path)) = &self.profile {
+            apply(Setting::Profile, &|| window.set_profile(name, path));
+        }
+        if let Some(proxy) = &self.proxy {
+            apply(Setting::Proxy, &|| window.set_proxy(proxy));
+        }
+        if let Some(port) = self.port {
+            apply(Setting::Port, &|| window.set_port(port));
+        }
+        if let Some(status) = self.kiosk {
+            window.set_kiosk(status);
+        }
+        if let Some(status) = self.hidden {
+            window.set_hide(status);
+        }
+        if let Some((width, height)) = self.size {
+            apply(Setting::Size, &|| {
+                window.set_size(width, height);
+                Ok(())
+            });
+        }
+        if let Some((x, y)) = self.position {
+            window.set_position(x, y);
+        }
+        if let Some((icon, kind)) = &self.icon {
+            apply(Setting::Icon, &|| window.set_icon(icon, kind));
+        }
+        if let Some(status) = self.event_blocking {
+            window.set_event_blocking(status);
+        }
+
+        let shown = window.show_browser(content, browser).is_ok();
+
+        if shown && failed.is_empty() {
+            Ok(window)
+        } else {
+            Err(BuildError {
+                window,
+                failed,
+                shown,
+            })
+        }
+    }
+}
+
+fn has_nul(s: &str) -> bool {
+    s.contains('\0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let builder = WindowBuilder::new()
+            .size(0, 600)
+            .port(70000)
+            .proxy("http://proxy:8080")
+            .root_folder("does/not/exist");
+
+        let failed: Vec<Setting> = builder.validate().iter().map(|f| f.setting).collect();
+        assert_eq!(
+            failed,
+            vec![Setting::RootFolder, Setting::Port, Setting::Size]
+        );
+        assert!(WindowBuilder::new().size(800, 600).validate().is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use crate::bindgen::*;
+use crate::cookies::Cookie;
+use crate::error::{ScriptError, ScriptErrorKind, WebUiError};
+use crate::script::script_result;
+use crate::window::WindowRef;
+
+/// Identifies a client of a window, see `Client::id`.
+pub type ClientId = usize;
+
+// Clients seen connected and not disconnected since
+static CONNECTED: LazyLock<Mutex<HashSet<Client>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+// Windows whose clients are tracked, see `WindowRef::track_clients`
+static TRACKED: LazyLock<Mutex<HashSet<usize>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// One browser tab connected to a window, see `Event::client`. Unlike the
+/// event it stays usable after the handler returns, until the tab disconnects.
+/// Its window must track its clients, which `Config::multi_client` turns on,
+/// see `WindowRef::track_clients`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Client {
+    window: usize,
+    client_id: usize,
+    connection_id: usize,
+}
+
+// Start tracking the clients of `window`, false if they already were
+pub(crate) fn track(window: usize) -> bool {
+    TRACKED.lock().unwrap().insert(window)
+}
+
+pub(crate) fn is_tracked(window: usize) -> bool {
+    TRACKED.lock().unwrap().contains(&window)
+}
+
+pub(crate) fn connected(client: Client) {
+    CONNECTED.lock().unwrap().insert(client);
+}
+
+pub(crate) fn disconnected(client: Client) {
+    CONNECTED.lock().unwrap().remove(&client);
+}
+
+pub(crate) fn clients_of(window: usize) -> Vec<Client> {
+    let mut clients: Vec<Client> = CONNECTED
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|client| client.window == window)
+        .copied()
+        .collect();
+    clients.sort_by_key(|client| (client.client_id, client.connection_id));
+    clients
+}
+
+pub(crate) fn forget_window(window: usize) {
+    TRACKED.lock().unwrap().remove(&window);
+    CONNECTED
+        .lock()
+        .unwrap()
+        .retain(|client| client.window != window);
+}
+
+impl Client {
+    pub(crate) fn new(window: usize, client_id: usize, connection_id: usize) -> Client {
+        Client {
+            window,
+            client_id,
+            connection_id,
+        }
+    }
+
+    pub fn window(&self) -> WindowRef {
+        WindowRef::from_id(self.window)
+    }
+
+    pub fn id(&self) -> ClientId {
+        self.client_id
+    }
+
+    pub fn connection_id(&self) -> usize {
+        self.connection_id
+    }
+
+    pub fn is_connected(&self) -> bool {
+        CONNECTED.lock().unwrap().contains(self)
+    }
+
+    // The `_client` functions only read the window and the client ids
+    fn event(&self) -> Result<webui_event_t, WebUiError> {
+        if !is_tracked(self.window) {
+            return Err(WebUiError::ClientsNotTracked(self.window));
+        }
+        if !self.is_connected() {
+            return Err(WebUiError::ClientDisconnected(self.client_id));
+        }
+        Ok(self.raw_event())
+    }
+
+    fn raw_event(&self) -> webui_event_t {
+        webui_event_t {
+            window: self.window,
+            event_type: 0,
+            element: ptr::null_mut(),
+            event_number: 0,
+            bind_id: 0,
+            client_id: self.client_id,
+            connection_id: self.connection_id,
+            cookies: ptr::null_mut(),
+        }
+    }
+
+    // Run `script` even if the window does not track its clients, to reply
+    // to a call of this client. WebUI ignores it once the tab is gone.
+    pub(crate) fn reply(&self, script: &str) -> Result<(), WebUiError> {
+        let script_c_str = CString::new(script)?;
+        let script_c_char: *const c_char = script_c_str.as_ptr() as *const c_char;
+        let mut event = self.raw_event();
+
+        unsafe {
+            webui_run_client(&mut event, script_c_char);
+        }
+        Ok(())
+    }
+
+    // Set or expire a cookie on this client
+    pub fn set_cookie(&self, cookie: &Cookie) -> Result<(), WebUiError> {
+        self.run(&cookie.script()?)
+    }
+
+    pub fn show(&self, content: &str) -> Result<(), WebUiError> {
+        let content_c_str = CString::new(content)?;
+        let content_c_char: *const c_char = content_c_str.as_ptr() as *const c_char;
+        let mut event = self.event()?;
+
+        unsafe {
+            match webui_show_client(&mut event, content_c_char) {
+                true => Ok(()),
+                false => Err(WebUiError::ShowFailed),
+            }
+        }
+    }
+
+    pub fn close(&self) -> Result<(), WebUiError> {
+        let mut event = self.event()?;
+
+        unsafe {
+            webui_close_client(&mut event);
+        }
+        Ok(())
+    }
+
+    pub fn send_raw(&self, function: &str, data: &[u8]) -> Result<(), WebUiError> {
+        let size = data.len();
+        let raw = data.as_ptr() as *mut std::os::raw::c_void;
+        let function_c_str = CString::new(function)?;
+        let function_c_char: *const c_char = function_c_str.as_ptr() as *const c_char;
+        let mut event = self.event()?;
+
+        unsafe {
+            webui_send_raw_client(&mut event, function_c_char, raw, size);
+        }
+        Ok(())
+    }
+
+    pub fn navigate(&self, url: &str) -> Result<(), WebUiError> {
+        let url_c_str = CString::new(url)?;
+        let url_c_char: *const c_char = url_c_str.as_ptr() as *const c_char;
+        let mut event = self.event()?;
+
+        unsafe {
+            webui_navigate_client(&mut event, url_c_char);
+        }
+        Ok(())
+    }
+
+    pub fn run(&self, script: &str) -> Result<(), WebUiError> {
+        let script_c_str = CString::new(script)?;
+        let script_c_char: *const c_char = script_c_str.as_ptr() as *const c_char;
+        let mut event = self.event()?;
+
+        unsafe {
+            webui_run_client(&mut event, script_c_char);
+        }
+        Ok(())
+    }
+
+    pub fn script(
+        &self,
+        script: &str,
+        timeout: usize,
+        buffer_length: usize,
+    ) -> Result<String, WebUiError> {
+        let script_c_str = CString::new(crate::script::catch_exceptions(script))?;
+        let script_c_char: *const c_char = script_c_str.as_ptr() as *const c_char;
+
+        // Reported like any other script failure
+        let mut event = self.event().map_err(|err| match err {
+            WebUiError::ClientDisconnected(_) => {
+                ScriptError::new(ScriptErrorKind::Disconnected, "").into()
+            }
+            err => err,
+        })?;
+
+        let mut buffer = vec![0u8; buffer_length];
+
+        unsafe {
+            let success = webui_script_client(
+                &mut event,
+                script_c_char,
+                timeout,
+                buffer.as_mut_ptr() as *mut c_char,
+                buffer_length,
+            );
+            script_result(success, buffer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disconnected_client_is_rejected() {
+        assert_eq!(
+            Client::new(701, 3, 12).run("alert(1)"),
+            Err(WebUiError::ClientsNotTracked(701))
+        );
+
+        track(700);
+        let client = Client::new(700, 3, 12);
+        connected(client);
+        assert!(client.is_connected());
+        assert!(!Client::new(700, 3, 13).is_connected());
+        assert_eq!(clients_of(700), vec![client]);
+
+        disconnected(client);
+        assert_eq!(
+            client.run("alert(1)"),
+            Err(WebUiError::ClientDisconnected(3))
+        );
+        forget_window(700);
+    }
+}
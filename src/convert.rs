@@ -0,0 +1,120 @@
+// `TryFrom<usize>`, `FromStr` and `Display` for the webui enums, so they can
+// be read from config files. Names are matched ignoring case, `-` and `_`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::WebUiError;
+use crate::events::EventType;
+use crate::Browser;
+use crate::Runtime;
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '-' && *c != '_')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+macro_rules! impl_conversions {
+    ($ty:ident, $kind:literal, { $($value:literal => $variant:ident as $name:literal),* $(,)? }) => {
+        impl TryFrom<usize> for $ty {
+            type Error = WebUiError;
+
+            fn try_from(value: usize) -> Result<Self, WebUiError> {
+                match value {
+                    $($value => Ok($ty::$variant),)*
+                    _ => Err(WebUiError::UnknownVariant {
+                        kind: $kind,
+                        value: value.to_string(),
+                    }),
+                }
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = WebUiError;
+
+            fn from_str(s: &str) -> Result<Self, WebUiError> {
+                let name = normalize(s.trim());
+                $(
+                    if name == normalize($name) {
+                        return Ok($ty::$variant);
+                    }
+                )*
+                Err(WebUiError::UnknownVariant {
+                    kind: $kind,
+                    value: s.to_owned(),
+                })
+            }
+        }
+
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $($ty::$variant => f.write_str($name),)*
+                }
+            }
+        }
+    };
+}
+
+impl_conversions!(Browser, "browser", {
+    0 => NoBrowser as "no-browser",
+    1 => AnyBrowser as "any-browser",
+    2 => Chrome as "chrome",
+    3 => Firefox as "firefox",
+    4 => Edge as "edge",
+    5 => Safari as "safari",
+    6 => Chromium as "chromium",
+    7 => Opera as "opera",
+    8 => Brave as "brave",
+    9 => Vivaldi as "vivaldi",
+    10 => Epic as "epic",
+    11 => Yandex as "yandex",
+    12 => ChromiumBased as "chromium-based",
+    13 => Webview as "webview",
+});
+
+impl_conversions!(Runtime, "runtime", {
+    0 => None as "none",
+    1 => Deno as "deno",
+    2 => NodeJS as "nodejs",
+    3 => Bun as "bun",
+});
+
+impl_conversions!(EventType, "event type", {
+    0 => WEBUI_EVENT_DISCONNECTED as "disconnected",
+    1 => WEBUI_EVENT_CONNECTED as "connected",
+    2 => WEBUI_EVENT_MOUSE_CLICK as "mouse-click",
+    3 => WEBUI_EVENT_NAVIGATION as "navigation",
+    4 => WEBUI_EVENT_CALLBACK as "callback",
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Browser::try_from(2), Ok(Browser::Chrome));
+        assert!(Browser::try_from(14).is_err());
+        assert_eq!("Chromium_Based".parse(), Ok(Browser::ChromiumBased));
+        assert_eq!(" firefox ".parse(), Ok(Browser::Firefox));
+        assert_eq!("NodeJS".parse(), Ok(Runtime::NodeJS));
+        assert_eq!("mouseclick".parse(), Ok(EventType::WEBUI_EVENT_MOUSE_CLICK));
+        assert_eq!(
+            "netscape".parse::<Browser>(),
+            Err(WebUiError::UnknownVariant {
+                kind: "browser",
+                value: "netscape".to_owned(),
+            })
+        );
+
+        for value in 0..=13 {
+            let browser = Browser::try_from(value).unwrap();
+            assert_eq!(browser as usize, value);
+            assert_eq!(browser.to_string().parse(), Ok(browser));
+        }
+    }
+}
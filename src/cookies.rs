@@ -0,0 +1,223 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::WebUiError;
+use crate::script::json_quote;
+
+/// Cookies sent by a client, parsed from `Event::cookies`. Only filled when
+/// `Config::use_cookies` is enabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieJar {
+    cookies: Vec<(String, String)>,
+}
+
+impl CookieJar {
+    // Parse a `Cookie` header, e.g. `session=abc; theme=dark`
+    pub fn parse(header: &str) -> CookieJar {
+        let cookies = header
+            .split(';')
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                let name = name.trim();
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+
+                match name.is_empty() {
+                    true => None,
+                    false => Some((name.to_owned(), value.to_owned())),
+                }
+            })
+            .collect();
+
+        CookieJar { cookies }
+    }
+
+    // Browsers send the most specific cookie first when names collide
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.cookies
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.cookies.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// A cookie to set on a client, with `Event::set_cookie` or `Client::set_cookie`.
+/// Its `Display` is the value of a `Set-Cookie` header, for file handlers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            path: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    // A cookie that removes `name` from the client
+    pub fn expired(name: &str) -> Cookie {
+        Cookie::new(name, "").max_age(Duration::ZERO)
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    // Without a max age the cookie lasts for the browser session
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn secure(mut self, status: bool) -> Self {
+        self.secure = status;
+        self
+    }
+
+    // Only for headers, `set_cookie` rejects HttpOnly cookies as browsers
+    // ignore them when set from a script
+    pub fn http_only(mut self, status: bool) -> Self {
+        self.http_only = status;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    // Names are HTTP tokens, values must not need quoting
+    pub fn is_valid(&self) -> bool {
+        let token = |c: char| c.is_ascii_graphic() && !"()<>@,;:\\\"/[]?={}".contains(c);
+        let value = |c: char| c.is_ascii_graphic() && !"\",;\\".contains(c);
+        let attribute = |s: &str| !s.chars().any(|c| c.is_ascii_control() || c == ';');
+
+        !self.name.is_empty()
+            && self.name.chars().all(token)
+            && self.value.chars().all(value)
+            && self.path.as_deref().is_none_or(attribute)
+    }
+
+    // Script setting the cookie through `document.cookie`
+    pub(crate) fn script(&self) -> Result<String, WebUiError> {
+        if !self.is_valid() || self.http_only {
+            return Err(WebUiError::InvalidCookie(self.name.clone()));
+        }
+        Ok(format!(
+            "document.cookie = {};",
+            json_quote(&self.to_string())
+        ))
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => write!(f, "; SameSite=Strict"),
+            Some(SameSite::Lax) => write!(f, "; SameSite=Lax"),
+            Some(SameSite::None) => write!(f, "; SameSite=None"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cookie_jar() {
+        let jar = CookieJar::parse("session=abc123; theme=\"dark\";; =x; session=old; flag");
+        assert_eq!(jar.get("session"), Some("abc123"));
+        assert_eq!(jar.get("theme"), Some("dark"));
+        assert_eq!(jar.get("flag"), None);
+        assert_eq!(jar.len(), 3);
+        assert!(CookieJar::parse("").is_empty());
+    }
+
+    #[test]
+    fn test_cookie_display() {
+        let cookie = Cookie::new("session", "abc123")
+            .path("/")
+            .max_age(Duration::from_secs(3600))
+            .same_site(SameSite::Lax);
+        assert_eq!(
+            cookie.to_string(),
+            "session=abc123; Path=/; Max-Age=3600; SameSite=Lax"
+        );
+        assert_eq!(
+            Cookie::expired("session").to_string(),
+            "session=; Max-Age=0"
+        );
+        assert!(cookie.is_valid());
+        assert!(!Cookie::new("session", "a;b").is_valid());
+        assert!(!Cookie::new("", "a").is_valid());
+
+        assert!(cookie.script().is_ok());
+        assert_eq!(
+            cookie.http_only(true).script(),
+            Err(WebUiError::InvalidCookie("session".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::ffi::NulError;
+use std::fmt;
+
+/// Errors returned by the webui wrappers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebUiError {
+    // A string passed to webui contains a NUL byte at `position`
+    InvalidString { position: usize },
+    ShowFailed,
+    ServerFailed,
+    PortUnavailable(usize),
+    RootFolderRejected(String),
+    ScriptError(ScriptError),
+    TlsRejected,
+    WindowNotFound(usize),
+    EncodingFailed,
+    // The client with this id has disconnected
+    ClientDisconnected(usize),
+    // The clients of the window with this id are not tracked, so whether a
+    // client is connected is unknown
+    ClientsNotTracked(usize),
+    // The cookie with this name has an invalid name, value or path, or is
+    // HttpOnly and cannot be set from a script
+    InvalidCookie(String),
+    // `value` names no variant of `kind`, e.g. no browser
+    UnknownVariant { kind: &'static str, value: String },
+    // A script result could not be read as JSON
+    InvalidJson(String),
+    // Not a JavaScript function name
+    InvalidIdentifier(String),
+    // A `js!` template that cannot be expanded safely
+    InvalidTemplate(String),
+}
+
+impl fmt::Display for WebUiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebUiError::InvalidString { position } => {
+                write!(f, "string contains a NUL byte at position {}", position)
+            }
+            WebUiError::ShowFailed => write!(f, "window could not be shown"),
+            WebUiError::ServerFailed => write!(f, "web server could not be started"),
+            WebUiError::PortUnavailable(port) => write!(f, "port {} is not available", port),
+            WebUiError::RootFolderRejected(folder) => {
+                write!(f, "root folder {:?} was rejected", folder)
+            }
+            WebUiError::ScriptError(err) => err.fmt(f),
+            WebUiError::TlsRejected => write!(f, "TLS certificate or key was rejected"),
+            WebUiError::WindowNotFound(id) => write!(f, "window {} not found", id),
+            WebUiError::EncodingFailed => write!(f, "base64 encoding failed"),
+            WebUiError::ClientDisconnected(id) => write!(f, "client {} is disconnected", id),
+            WebUiError::ClientsNotTracked(id) => {
+                write!(f, "clients of window {} are not tracked", id)
+            }
+            WebUiError::InvalidCookie(name) => write!(f, "cookie {:?} is invalid", name),
+            WebUiError::UnknownVariant { kind, value } => write!(f, "unknown {} {:?}", kind, value),
+            WebUiError::InvalidJson(message) => write!(f, "invalid JSON: {}", message),
+            WebUiError::InvalidIdentifier(name) => {
+                write!(f, "{:?} is not a JavaScript function name", name)
+            }
+            WebUiError::InvalidTemplate(reason) => write!(f, "invalid script template: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for WebUiError {}
+
+impl From<ScriptError> for WebUiError {
+    fn from(err: ScriptError) -> Self {
+        WebUiError::ScriptError(err)
+    }
+}
+
+impl From<NulError> for WebUiError {
+    fn from(err: NulError) -> Self {
+        WebUiError::InvalidString {
+            position: err.nul_position(),
+        }
+    }
+}
+
+/// Why a script run with `script` failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptErrorKind {
+    Timeout,
+    // The script threw, or could not be parsed
+    Exception,
+    // No client was connected to run the script
+    Disconnected,
+    // The result filled the whole buffer and may have been cut
+    Truncated,
+    // The result could not be read back, or the script worker panicked
+    Internal,
+}
+
+/// A failed script, with the message and stack of the JavaScript exception
+/// for `ScriptErrorKind::Exception`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub kind: ScriptErrorKind,
+    pub message: String,
+    pub stack: Option<String>,
+}
+
+impl ScriptError {
+    pub fn new(kind: ScriptErrorKind, message: &str) -> ScriptError {
+        ScriptError {
+            kind,
+            message: message.to_owned(),
+            stack: None,
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ScriptErrorKind::Timeout => write!(f, "script timed out"),
+            ScriptErrorKind::Exception => write!(f, "script threw: {}", self.message),
+            ScriptErrorKind::Disconnected => write!(f, "no client connected to run the script"),
+            ScriptErrorKind::Truncated => {
+                write!(f, "script result may have been cut by the buffer")
+            }
+            ScriptErrorKind::Internal => write!(f, "script failed: {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
@@ -1,23 +1,22 @@
 use std::ffi::CString;
+use std::marker::PhantomData;
 use std::os::raw::c_char;
 
 use crate::bindgen::*;
 use crate::char_to_string;
+use crate::client::Client;
+use crate::cookies::{Cookie, CookieJar};
+use crate::error::WebUiError;
+use crate::script::script_result;
+use crate::window::WindowRef;
 
 // Events
 pub type EventType = webui_event;
 
-// Implement into<usize>
+// Unknown values are read as callbacks, see `TryFrom<usize>` for a strict read
 impl EventType {
     pub fn from_usize(value: usize) -> EventType {
-        match value {
-            0 => EventType::WEBUI_EVENT_DISCONNECTED,
-            1 => EventType::WEBUI_EVENT_CONNECTED,
-            2 => EventType::WEBUI_EVENT_MOUSE_CLICK,
-            3 => EventType::WEBUI_EVENT_NAVIGATION,
-            4 => EventType::WEBUI_EVENT_CALLBACK,
-            _ => EventType::WEBUI_EVENT_CALLBACK,
-        }
+        EventType::try_from(value).unwrap_or(EventType::WEBUI_EVENT_CALLBACK)
     }
 }
 
@@ -30,14 +29,15 @@ pub struct EventSimple {
 }
 
 impl EventSimple {
-    pub fn set_response(&self, response: &str) {
+    pub fn set_response(&self, response: &str) -> Result<(), WebUiError> {
         // interface_set_response(self.window, self.event_number, response);
-        let response_c_str = CString::new(response).unwrap();
+        let response_c_str = CString::new(response)?;
         let response_c_char: *const c_char = response_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_interface_set_response(self.win, self.event_number, response_c_char);
         }
+        Ok(())
     }
 
     pub fn get_string_at(&self, index: usize) -> String {
@@ -64,7 +64,10 @@ impl EventSimple {
     }
 }
 
-pub struct Event {
+/// An event received by a handler. It borrows webui memory that is freed
+/// once the handler returns, so it cannot outlive the call; use `info` or
+/// `client` to keep what is needed for later.
+pub struct Event<'a> {
     pub win: usize,
     pub event_type: EventType,
     pub element: String,
@@ -74,10 +77,34 @@ pub struct Event {
     pub connection_id: usize,
     pub cookies: String,
     event: *mut webui_event_t,
+    callback: PhantomData<&'a mut webui_event_t>,
+}
+
+/// Owned copy of an event, which can be kept and sent to other threads
+/// after the handler returned.
+#[derive(Debug, Clone)]
+pub struct EventInfo {
+    pub win: usize,
+    pub event_type: EventType,
+    pub element: String,
+    pub bind_id: usize,
+    pub client: Client,
+    pub cookies: String,
+    pub args: Vec<String>,
+}
+
+impl EventInfo {
+    pub fn cookie_jar(&self) -> CookieJar {
+        CookieJar::parse(&self.cookies)
+    }
 }
 
-impl Event {
-    pub fn new(event: *mut webui_event_t) -> Event {
+impl Event<'_> {
+    /// # Safety
+    ///
+    /// `event` must point to the event passed to a webui callback, and the
+    /// returned `Event` must not be used after that callback returned.
+    pub(crate) unsafe fn from_raw<'a>(event: *mut webui_event_t) -> Event<'a> {
         unsafe {
             let win = (*event).window;
             let event_type = EventType::from_usize((*event).event_type);
@@ -98,17 +125,56 @@ impl Event {
                 connection_id,
                 cookies,
                 event,
+                callback: PhantomData,
             }
         }
     }
 
-    pub fn show_client(&self, content: impl AsRef<str> + Into<Vec<u8>>) -> bool {
+    // Copy the event and its arguments for use after the handler returns
+    pub fn info(&self) -> EventInfo {
+        EventInfo {
+            win: self.win,
+            event_type: self.event_type,
+            element: self.element.clone(),
+            bind_id: self.bind_id,
+            client: self.client(),
+            cookies: self.cookies.clone(),
+            args: (0..self.get_count())
+                .map(|index| self.get_string_at(index))
+                .collect(),
+        }
+    }
+
+    // The window the event comes from, for use inside handlers
+    pub fn window(&self) -> WindowRef {
+        WindowRef::from_id(self.win)
+    }
+
+    // The client that sent the event, usable after the handler returns if
+    // its window tracks its clients, see `Client`
+    pub fn client(&self) -> Client {
+        Client::new(self.win, self.client_id, self.connection_id)
+    }
+
+    pub fn cookie_jar(&self) -> CookieJar {
+        CookieJar::parse(&self.cookies)
+    }
+
+    // Set or expire a cookie on the client that sent the event
+    pub fn set_cookie(&self, cookie: &Cookie) -> Result<(), WebUiError> {
+        self.run(&cookie.script()?)
+    }
+
+    pub fn show_client(&self, content: impl AsRef<str> + Into<Vec<u8>>) -> Result<(), WebUiError> {
         unsafe {
             // Content String to i8/u8
-            let content_c_str = CString::new(content).unwrap();
+            let content_c_str = CString::new(content)?;
             let content_c_char: *const c_char = content_c_str.as_ptr() as *const c_char;
 
-            webui_show_client(self.event, content_c_char)
+            match webui_show_client(self.event, content_c_char) {
+                true => Ok(()),
+                false => Err(WebUiError::ShowFailed),
+            }
         }
     }
 
@@ -118,53 +184,58 @@ impl Event {
         }
     }
 
-    pub fn send_raw(&self, function: &str, data: &[u8]) {
+    pub fn send_raw(&self, function: &str, data: &[u8]) -> Result<(), WebUiError> {
         let size = data.len();
         let raw = data.as_ptr() as *mut std::os::raw::c_void;
-        let function_c_str = CString::new(function).unwrap();
+        let function_c_str = CString::new(function)?;
         let function_c_char: *const c_char = function_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_send_raw_client(self.event, function_c_char, raw, size);
         }
+        Ok(())
     }
 
-    pub fn navigate_client(&self, url: &str) {
-        let url_c_str = CString::new(url).unwrap();
+    pub fn navigate_client(&self, url: &str) -> Result<(), WebUiError> {
+        let url_c_str = CString::new(url)?;
         let url_c_char: *const c_char = url_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_navigate_client(self.event, url_c_char);
         }
+        Ok(())
     }
 
-    pub fn run(&self, script: &str) {
-        let script_c_str = CString::new(script).unwrap();
+    pub fn run(&self, script: &str) -> Result<(), WebUiError> {
+        let script_c_str = CString::new(script)?;
         let script_c_char: *const c_char = script_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_run_client(self.event, script_c_char);
         }
+        Ok(())
     }
 
-    pub fn script(&self, script: &str, timeout: usize, buffer_length: usize) -> Result<String, ()> {
-        let script_c_str = CString::new(script).unwrap();
+    pub fn script(
+        &self,
+        script: &str,
+        timeout: usize,
+        buffer_length: usize,
+    ) -> Result<String, WebUiError> {
+        let script_c_str = CString::new(crate::script::catch_exceptions(script))?;
         let script_c_char: *const c_char = script_c_str.as_ptr() as *const c_char;
 
-        let buffer_c_str = CString::new(vec![0; buffer_length]).unwrap();
-        let buffer_c_char: *mut c_char = buffer_c_str.as_ptr() as *mut c_char;
+        let mut buffer = vec![0u8; buffer_length];
 
         unsafe {
-            match webui_script_client(
+            let success = webui_script_client(
                 self.event,
                 script_c_char,
                 timeout,
-                buffer_c_char,
+                buffer.as_mut_ptr() as *mut c_char,
                 buffer_length,
-            ) {
-                true => Ok(char_to_string(buffer_c_char)),
-                false => Err(()),
-            }
+            );
+            script_result(success, buffer)
         }
     }
 
@@ -230,13 +301,14 @@ impl Event {
         }
     }
 
-    pub fn return_string(&self, value: &str) {
-        let value_c_str = CString::new(value).unwrap();
+    pub fn return_string(&self, value: &str) -> Result<(), WebUiError> {
+        let value_c_str = CString::new(value)?;
         let value_c_char: *const c_char = value_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_return_string(self.event, value_c_char);
         }
+        Ok(())
     }
 
     pub fn return_bool(&self, value: bool) {
@@ -0,0 +1,238 @@
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+
+pub type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Runs the futures returned by async handlers.
+pub trait Executor: Send + Sync + 'static {
+    fn spawn(&self, future: BoxFuture);
+}
+
+// Lets a closure forward to another runtime, e.g. `|f| { handle.spawn(f); }`
+impl<F> Executor for F
+where
+    F: Fn(BoxFuture) + Send + Sync + 'static,
+{
+    fn spawn(&self, future: BoxFuture) {
+        self(future)
+    }
+}
+
+struct Task {
+    future: Mutex<Option<BoxFuture>>,
+    queue: Sender<Arc<Task>>,
+}
+
+impl Task {
+    fn run(self: Arc<Self>) {
+        let mut slot = self.future.lock().unwrap();
+        if let Some(mut future) = slot.take() {
+            let waker = Waker::from(self.clone());
+            let mut context = Context::from_waker(&waker);
+
+            // A panicking future is dropped, the worker thread keeps running
+            let poll = panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut context)));
+            if let Ok(Poll::Pending) = poll {
+                *slot = Some(future);
+            }
+        }
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        let _ = self.queue.send(self.clone());
+    }
+}
+
+/// Built-in executor, polling futures on a fixed set of worker threads.
+pub struct ThreadPool {
+    queue: Sender<Arc<Task>>,
+}
+
+impl ThreadPool {
+    pub fn new(threads: usize) -> ThreadPool {
+        let (queue, receiver) = channel::<Arc<Task>>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("webui-rs-worker-{}", i))
+                .spawn(move || worker(&receiver))
+                .expect("failed to spawn webui-rs worker thread");
+        }
+
+        ThreadPool { queue }
+    }
+}
+
+impl Default for ThreadPool {
+    fn default() -> Self {
+        let threads = thread::available_parallelism().map_or(4, |n| n.get());
+        ThreadPool::new(threads)
+    }
+}
+
+impl Executor for ThreadPool {
+    fn spawn(&self, future: BoxFuture) {
+        let task = Arc::new(Task {
+            future: Mutex::new(Some(future)),
+            queue: self.queue.clone(),
+        });
+        let _ = self.queue.send(task);
+    }
+}
+
+fn worker(receiver: &Mutex<Receiver<Arc<Task>>>) {
+    loop {
+        let task = match receiver.lock().unwrap().recv() {
+            Ok(task) => task,
+            Err(_) => return,
+        };
+        task.run();
+    }
+}
+
+static EXECUTOR: LazyLock<RwLock<Option<Arc<dyn Executor>>>> = LazyLock::new(|| RwLock::new(None));
+
+pub(crate) fn set_executor(executor: Arc<dyn Executor>) {
+    *EXECUTOR.write().unwrap() = Some(executor);
+}
+
+// The user executor, or the built-in pool started on first use
+pub(crate) fn executor() -> Arc<dyn Executor> {
+    if let Some(executor) = EXECUTOR.read().unwrap().as_ref() {
+        return executor.clone();
+    }
+
+    EXECUTOR
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(ThreadPool::default()))
+        .clone()
+}
+
+// Shared between a `Blocking` future and its thread
+struct BlockingState<T> {
+    output: Option<Option<T>>,
+    waker: Option<Waker>,
+}
+
+/// Future running a blocking function on its own thread, started on the
+/// first poll. Resolves to `None` if the function panicked.
+pub(crate) struct Blocking<T> {
+    func: Option<Box<dyn FnOnce() -> T + Send + 'static>>,
+    state: Arc<Mutex<BlockingState<T>>>,
+}
+
+pub(crate) fn spawn_blocking<T, F>(func: F) -> Blocking<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+{
+    Blocking {
+        func: Some(Box::new(func)),
+        state: Arc::new(Mutex::new(BlockingState {
+            output: None,
+            waker: None,
+        })),
+    }
+}
+
+impl<T: Send + 'static> Future for Blocking<T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(output) = state.output.take() {
+                return Poll::Ready(output);
+            }
+            state.waker = Some(cx.waker().clone());
+        }
+
+        if let Some(func) = self.func.take() {
+            let state = self.state.clone();
+            let spawned = thread::Builder::new()
+                .name("webui-rs-blocking".to_owned())
+                .spawn(move || {
+                    let output = panic::catch_unwind(AssertUnwindSafe(func)).ok();
+                    let mut state = state.lock().unwrap();
+                    state.output = Some(output);
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                });
+            if spawned.is_err() {
+                return Poll::Ready(None);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Run `future` on the executor and wait for its output
+    fn run_to_completion<F>(future: F) -> Option<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        executor().spawn(Box::pin(async move {
+            let _ = sender.send(future.await);
+        }));
+        receiver.recv().ok()
+    }
+
+    // Pending on its first poll, ready on the next one
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_thread_pool_runs_futures() {
+        let pool = ThreadPool::new(2);
+        let (sender, receiver) = channel();
+
+        for i in 0..8 {
+            let sender = sender.clone();
+            pool.spawn(Box::pin(async move {
+                YieldOnce(false).await;
+                sender.send(i).unwrap();
+            }));
+        }
+
+        let mut results: Vec<i32> = receiver.iter().take(8).collect();
+        results.sort();
+        assert_eq!(results, (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_spawn_blocking() {
+        assert_eq!(run_to_completion(spawn_blocking(|| 6 * 7)), Some(Some(42)));
+        assert_eq!(
+            run_to_completion(spawn_blocking(|| -> i32 { panic!("oops") })),
+            Some(None)
+        );
+    }
+}
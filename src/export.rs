@@ -0,0 +1,59 @@
+// Registry filled by the `#[webui::export]` attribute
+use crate::error::WebUiError;
+use crate::window::{BindHandle, WindowRef};
+
+#[doc(hidden)]
+pub use inventory;
+
+/// A parameter of an exported function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Param {
+    pub name: &'static str,
+    pub ty: &'static str,
+}
+
+/// A function exported with `#[webui::export]`.
+pub struct Export {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    pub returns: &'static str,
+    pub register: fn(&WindowRef) -> Result<BindHandle, WebUiError>,
+}
+
+inventory::collect!(Export);
+
+impl Export {
+    // JavaScript function forwarding to `webui.call()`
+    pub fn js_stub(&self) -> String {
+        let params: Vec<&str> = self.params.iter().map(|param| param.name).collect();
+        let args: String = params.iter().map(|param| format!(", {}", param)).collect();
+
+        format!(
+            "function {}({}) {{ return webui.call(\"{}\"{}); }}",
+            self.name,
+            params.join(", "),
+            self.name,
+            args
+        )
+    }
+}
+
+pub fn exports() -> impl Iterator<Item = &'static Export> {
+    inventory::iter::<Export>.into_iter()
+}
+
+// Bind every exported function to `win` for the window's lifetime,
+// `Window::unbind` removes them one by one
+pub fn register_all(win: &WindowRef) -> Result<(), WebUiError> {
+    for export in exports() {
+        (export.register)(win)?.forget();
+    }
+    Ok(())
+}
+
+// JavaScript stubs of every exported function, to serve with the page
+pub fn js_bindings() -> String {
+    let mut stubs: Vec<String> = exports().map(Export::js_stub).collect();
+    stubs.sort();
+    stubs.join("\n")
+}
@@ -0,0 +1,644 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+
+use crate::client::Client;
+use crate::error::WebUiError;
+use crate::events::Event;
+use crate::script::json_quote;
+
+// Error raised while decoding the arguments of a `webui.call()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgError {
+    Count {
+        expected: usize,
+        got: usize,
+    },
+    Type {
+        index: usize,
+        expected: &'static str,
+        value: String,
+    },
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgError::Count { expected, got } => {
+                write!(f, "expected {} argument(s), got {}", expected, got)
+            }
+            ArgError::Type {
+                index,
+                expected,
+                value,
+            } => write!(
+                f,
+                "argument {} should be {}, got {:?}",
+                index, expected, value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+/// The arguments of a `webui.call()`, as read by `FromEventArg`.
+pub trait EventArgs {
+    fn get_count(&self) -> usize;
+    fn get_string_at(&self, index: usize) -> String;
+}
+
+impl EventArgs for Event<'_> {
+    fn get_count(&self) -> usize {
+        Event::get_count(self)
+    }
+
+    fn get_string_at(&self, index: usize) -> String {
+        Event::get_string_at(self, index)
+    }
+}
+
+/// A value that can be read from the argument at `index` of a `webui.call()`.
+pub trait FromEventArg: Sized {
+    fn from_event_arg(args: &dyn EventArgs, index: usize) -> Result<Self, ArgError>;
+
+    // Value used when the caller passed fewer arguments, `None` if required
+    fn from_missing_arg() -> Option<Self> {
+        None
+    }
+}
+
+/// Where a return value is sent: the event, or the later reply to a call
+/// answered by `Window::bind_async`.
+pub trait EventReturn {
+    fn return_int(&self, value: i64);
+    fn return_float(&self, value: f64);
+    fn return_bool(&self, value: bool);
+    fn return_string(&self, value: &str) -> Result<(), WebUiError>;
+}
+
+impl EventReturn for Event<'_> {
+    fn return_int(&self, value: i64) {
+        Event::return_int(self, value)
+    }
+
+    fn return_float(&self, value: f64) {
+        Event::return_float(self, value)
+    }
+
+    fn return_bool(&self, value: bool) {
+        Event::return_bool(self, value)
+    }
+
+    fn return_string(&self, value: &str) -> Result<(), WebUiError> {
+        Event::return_string(self, value)
+    }
+}
+
+/// A value that can be sent back to the JavaScript caller.
+pub trait IntoEventReturn {
+    fn into_event_return(self, event: &dyn EventReturn);
+}
+
+/// A function that can be bound with `Window::bind_typed`, `Args` being the
+/// tuple of its argument types.
+pub trait Handler<Args>: Send + Sync + 'static {
+    type Output: IntoEventReturn;
+
+    // Decode the arguments and call the function
+    fn invoke(&self, args: &dyn EventArgs) -> Result<Self::Output, ArgError>;
+
+    fn call(&self, event: &Event) -> Result<(), ArgError> {
+        self.invoke(event)?.into_event_return(event);
+        Ok(())
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(
+    args: &dyn EventArgs,
+    index: usize,
+    expected: &'static str,
+) -> Result<T, ArgError> {
+    let value = args.get_string_at(index);
+    value.trim().parse().map_err(|_| ArgError::Type {
+        index,
+        expected,
+        value,
+    })
+}
+
+macro_rules! impl_from_event_arg {
+    ($($ty:ty),*) => {
+        $(
+            impl FromEventArg for $ty {
+                fn from_event_arg(args: &dyn EventArgs, index: usize) -> Result<Self, ArgError> {
+                    parse_arg(args, index, stringify!($ty))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_event_arg!(i64, i32, u32, u64, usize, f64, f32, bool);
+
+impl FromEventArg for String {
+    fn from_event_arg(args: &dyn EventArgs, index: usize) -> Result<Self, ArgError> {
+        Ok(args.get_string_at(index))
+    }
+}
+
+impl<T: FromEventArg> FromEventArg for Option<T> {
+    // An explicit `null` or `undefined` reaches us as its name
+    fn from_event_arg(args: &dyn EventArgs, index: usize) -> Result<Self, ArgError> {
+        match args.get_string_at(index).as_str() {
+            "null" | "undefined" => Ok(None),
+            _ => T::from_event_arg(args, index).map(Some),
+        }
+    }
+
+    fn from_missing_arg() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl IntoEventReturn for () {
+    fn into_event_return(self, _event: &dyn EventReturn) {}
+}
+
+macro_rules! impl_into_event_return {
+    ($method:ident as $cast:ty: $($ty:ty),*) => {
+        $(
+            impl IntoEventReturn for $ty {
+                fn into_event_return(self, event: &dyn EventReturn) {
+                    event.$method(self as $cast);
+                }
+            }
+        )*
+    };
+}
+
+impl_into_event_return!(return_int as i64: i64, i32, u32, usize);
+impl_into_event_return!(return_float as f64: f64, f32);
+
+impl IntoEventReturn for bool {
+    fn into_event_return(self, event: &dyn EventReturn) {
+        event.return_bool(self);
+    }
+}
+
+impl IntoEventReturn for String {
+    fn into_event_return(self, event: &dyn EventReturn) {
+        self.as_str().into_event_return(event);
+    }
+}
+
+impl IntoEventReturn for &str {
+    fn into_event_return(self, event: &dyn EventReturn) {
+        if let Err(err) = event.return_string(self) {
+            return_error(event, "HANDLER", &format!("invalid return value: {}", err));
+        }
+    }
+}
+
+impl<T: IntoEventReturn> IntoEventReturn for Option<T> {
+    fn into_event_return(self, event: &dyn EventReturn) {
+        if let Some(value) = self {
+            value.into_event_return(event);
+        }
+    }
+}
+
+impl<T: IntoEventReturn, E: fmt::Display> IntoEventReturn for Result<T, E> {
+    fn into_event_return(self, event: &dyn EventReturn) {
+        match self {
+            Ok(value) => value.into_event_return(event),
+            Err(err) => return_error(event, "HANDLER", &err.to_string()),
+        }
+    }
+}
+
+// Marks a response as an error, see `CLIENT_SHIM`
+const ERROR_PREFIX: &str = "\u{1}webui-rs:error:";
+
+// Marks the id of a call answered later, see `PendingCall`
+const PENDING_PREFIX: &str = "\u{1}webui-rs:pending:";
+
+/// Script patching `webui.call()` so that error responses reject the promise
+/// with an `Error` carrying a `code`: `HANDLER` for an `Err` returned by the
+/// handler, `ARGUMENT` for arguments that could not be decoded, `JSON` for
+/// JSON that could not be (de)serialized and `PANIC` for a panic. Calls to
+/// `Window::bind_async` handlers also wait here for their later reply.
+///
+/// It is served with the HTML shown by windows using the typed bindings, other
+/// pages can add it with a `<script>` tag after `webui.js`.
+pub const CLIENT_SHIM: &str = r#"(function () {
+  if (typeof webui === "undefined" || webui.__webuiRsShim) return;
+  const prefix = "\u0001webui-rs:error:";
+  const pending = "\u0001webui-rs:pending:";
+  const waiting = new Map();
+  const settled = new Map();
+  const call = webui.call.bind(webui);
+  webui.__webuiRsSettle = function (id, response) {
+    const resolve = waiting.get(id);
+    waiting.delete(id);
+    if (resolve) resolve(response);
+    else settled.set(id, response);
+  };
+  webui.call = async function (...args) {
+    let response = await call(...args);
+    if (typeof response === "string" && response.startsWith(pending)) {
+      const id = response.slice(pending.length);
+      response = settled.has(id)
+        ? settled.get(id)
+        : await new Promise((resolve) => waiting.set(id, resolve));
+      settled.delete(id);
+    }
+    if (typeof response === "string" && response.startsWith(prefix)) {
+      const { code, message } = JSON.parse(response.slice(prefix.length));
+      const error = new Error(message);
+      error.code = code;
+      throw error;
+    }
+    return response;
+  };
+  webui.__webuiRsShim = true;
+})();"#;
+
+// Add `CLIENT_SHIM` to an HTML page right after its `webui.js` script, so
+// that it runs before the page calls `webui.call()`. `None` without one.
+pub(crate) fn with_client_shim(content: &str) -> Option<String> {
+    let script = content.find("webui.js")?;
+    let end = script + content[script..].find("</script>")? + "</script>".len();
+    Some(format!(
+        "{}<script>{}</script>{}",
+        &content[..end],
+        CLIENT_SHIM,
+        &content[end..]
+    ))
+}
+
+// Response sent back to the JavaScript caller when a call fails
+pub(crate) fn error_response(code: &str, message: &str) -> String {
+    format!(
+        "{}{{\"code\":{},\"message\":{}}}",
+        ERROR_PREFIX,
+        json_quote(code),
+        json_quote(message)
+    )
+}
+
+pub(crate) fn return_error(event: &dyn EventReturn, code: &str, message: &str) {
+    // Control characters are escaped, so this has no NUL byte
+    let _ = event.return_string(&error_response(code, message));
+}
+
+// Records a return value as the response webui would have sent
+#[derive(Default)]
+struct Response(RefCell<String>);
+
+impl EventReturn for Response {
+    fn return_int(&self, value: i64) {
+        *self.0.borrow_mut() = value.to_string();
+    }
+
+    fn return_float(&self, value: f64) {
+        *self.0.borrow_mut() = value.to_string();
+    }
+
+    fn return_bool(&self, value: bool) {
+        *self.0.borrow_mut() = value.to_string();
+    }
+
+    fn return_string(&self, value: &str) -> Result<(), WebUiError> {
+        if let Some(position) = value.find('\0') {
+            return Err(WebUiError::InvalidString { position });
+        }
+        *self.0.borrow_mut() = value.to_owned();
+        Ok(())
+    }
+}
+
+fn response_of<R: IntoEventReturn>(value: R) -> String {
+    let response = Response::default();
+    value.into_event_return(&response);
+    response.0.into_inner()
+}
+
+static NEXT_CALL: AtomicU64 = AtomicU64::new(1);
+
+// A call answered at once with `pending_response`, whose promise the client
+// shim settles when the reply is sent. That happens on drop, with an error if
+// neither `resolve` nor `reject` was called, e.g. the future was dropped.
+pub(crate) struct PendingCall {
+    client: Client,
+    id: u64,
+    response: Option<String>,
+}
+
+impl PendingCall {
+    pub(crate) fn new(event: &Event) -> PendingCall {
+        PendingCall {
+            client: event.client(),
+            id: NEXT_CALL.fetch_add(1, Ordering::Relaxed),
+            response: None,
+        }
+    }
+
+    pub(crate) fn pending_response(&self) -> String {
+        format!("{}{}", PENDING_PREFIX, self.id)
+    }
+
+    pub(crate) fn resolve<R: IntoEventReturn>(mut self, value: R) {
+        self.response = Some(response_of(value));
+    }
+
+    pub(crate) fn reject(mut self, code: &str, message: &str) {
+        self.response = Some(error_response(code, message));
+    }
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        let response = self
+            .response
+            .take()
+            .unwrap_or_else(|| error_response("HANDLER", "async handler was dropped"));
+        let script = format!(
+            "webui.__webuiRsSettle(\"{}\", {});",
+            self.id,
+            json_quote(&response)
+        );
+        let _ = self.client.reply(&script);
+    }
+}
+
+/// A panic caught while running a bound handler.
+#[derive(Debug, Clone)]
+pub struct HandlerPanic {
+    pub window: usize,
+    pub element: String,
+    pub message: String,
+}
+
+impl fmt::Display for HandlerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "handler for {:?} panicked: {}",
+            self.element, self.message
+        )
+    }
+}
+
+impl std::error::Error for HandlerPanic {}
+
+pub type PanicHook = dyn Fn(&HandlerPanic) + Send + Sync + 'static;
+
+static PANIC_HOOK: RwLock<Option<Arc<PanicHook>>> = RwLock::new(None);
+
+pub(crate) fn set_panic_hook(hook: Option<Arc<PanicHook>>) {
+    *PANIC_HOOK.write().unwrap_or_else(|e| e.into_inner()) = hook;
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_owned()
+    }
+}
+
+// Run a handler without letting a panic unwind into the C caller.
+// The panic hook, if any, is told about every panic caught here.
+pub(crate) fn catch_panic<R>(
+    window: usize,
+    element: &str,
+    f: impl FnOnce() -> R,
+) -> Result<R, HandlerPanic> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let panic = HandlerPanic {
+            window,
+            element: element.to_owned(),
+            message: panic_message(payload.as_ref()),
+        };
+
+        let hook = PANIC_HOOK.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(hook) = hook {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| hook(&panic)));
+        }
+
+        panic
+    })
+}
+
+// Future adapter applying `catch_panic` to every poll
+pub(crate) struct CatchPanic<F> {
+    future: Pin<Box<F>>,
+    window: usize,
+    element: String,
+}
+
+impl<F: Future> CatchPanic<F> {
+    pub(crate) fn new(future: F, window: usize, element: &str) -> CatchPanic<F> {
+        CatchPanic {
+            future: Box::pin(future),
+            window,
+            element: element.to_owned(),
+        }
+    }
+}
+
+impl<F: Future> Future for CatchPanic<F> {
+    type Output = Result<F::Output, HandlerPanic>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        match catch_panic(this.window, &this.element, || this.future.as_mut().poll(cx)) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
+
+macro_rules! impl_handler {
+    ($count:literal $(, $arg:ident)*) => {
+        #[allow(non_snake_case, unused_variables, unused_assignments, unused_mut)]
+        impl<F, R $(, $arg)*> Handler<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Send + Sync + 'static,
+            R: IntoEventReturn,
+            $($arg: FromEventArg,)*
+        {
+            type Output = R;
+
+            fn invoke(&self, args: &dyn EventArgs) -> Result<R, ArgError> {
+                let count = args.get_count();
+                if count > $count {
+                    return Err(ArgError::Count {
+                        expected: $count,
+                        got: count,
+                    });
+                }
+
+                let mut index = 0;
+                $(
+                    let $arg = if index < count {
+                        $arg::from_event_arg(args, index)?
+                    } else {
+                        $arg::from_missing_arg().ok_or(ArgError::Count {
+                            expected: $count,
+                            got: count,
+                        })?
+                    };
+                    index += 1;
+                )*
+
+                Ok((self)($($arg),*))
+            }
+        }
+    };
+}
+
+impl_handler!(0);
+impl_handler!(1, A1);
+impl_handler!(2, A1, A2);
+impl_handler!(3, A1, A2, A3);
+impl_handler!(4, A1, A2, A3, A4);
+impl_handler!(5, A1, A2, A3, A4, A5);
+impl_handler!(6, A1, A2, A3, A4, A5, A6);
+impl_handler!(7, A1, A2, A3, A4, A5, A6, A7);
+impl_handler!(8, A1, A2, A3, A4, A5, A6, A7, A8);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Stands in for the arguments of an event
+    impl EventArgs for Vec<&str> {
+        fn get_count(&self) -> usize {
+            self.len()
+        }
+
+        fn get_string_at(&self, index: usize) -> String {
+            self[index].to_owned()
+        }
+    }
+
+    #[test]
+    fn test_handler_checks_arguments() {
+        let add = |a: i64, b: i64| a + b;
+        let invoke = |args: Vec<&str>| Handler::<(i64, i64)>::invoke(&add, &args);
+
+        assert_eq!(invoke(vec!["1", " 2"]), Ok(3));
+        assert_eq!(
+            invoke(vec!["1"]),
+            Err(ArgError::Count {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert_eq!(
+            invoke(vec!["1", "2", "3"]),
+            Err(ArgError::Count {
+                expected: 2,
+                got: 3
+            })
+        );
+        assert_eq!(
+            invoke(vec!["1", "two"]),
+            Err(ArgError::Type {
+                index: 1,
+                expected: "i64",
+                value: "two".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_optional_arguments() {
+        let greet = |name: Option<String>, times: Option<u32>| format!("{:?} {:?}", name, times);
+        let invoke = |args: Vec<&str>| {
+            Handler::<(Option<String>, Option<u32>)>::invoke(&greet, &args).unwrap()
+        };
+
+        assert_eq!(invoke(vec![]), "None None");
+        assert_eq!(invoke(vec!["null", "undefined"]), "None None");
+        assert_eq!(invoke(vec!["Ada", "3"]), "Some(\"Ada\") Some(3)");
+        assert!(
+            Handler::<(Option<String>, Option<u32>)>::invoke(&greet, &vec!["Ada", "x"]).is_err()
+        );
+
+        let flag = |on: bool, ratio: f64| on && ratio > 0.5;
+        assert_eq!(
+            Handler::<(bool, f64)>::invoke(&flag, &vec!["true", "0.75"]),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_catch_panic_calls_hook() {
+        static CAUGHT: AtomicUsize = AtomicUsize::new(0);
+        set_panic_hook(Some(Arc::new(|panic: &HandlerPanic| {
+            assert_eq!(panic.element, "broken");
+            CAUGHT.fetch_add(1, Ordering::SeqCst);
+        })));
+
+        assert_eq!(catch_panic(1, "fine", || 42).unwrap(), 42);
+
+        let panic = catch_panic(1, "broken", || panic!("oops {}", 1)).unwrap_err();
+        assert_eq!(panic.window, 1);
+        assert_eq!(panic.message, "oops 1");
+        assert_eq!(CAUGHT.load(Ordering::SeqCst), 1);
+
+        set_panic_hook(None);
+    }
+
+    #[test]
+    fn test_with_client_shim() {
+        let page = "<head><script src=\"/webui.js\"></script><title>x</title></head>";
+        assert_eq!(
+            with_client_shim(page).unwrap(),
+            format!(
+                "<head><script src=\"/webui.js\"></script><script>{}</script><title>x</title></head>",
+                CLIENT_SHIM
+            )
+        );
+        assert_eq!(with_client_shim("index.html"), None);
+    }
+
+    #[test]
+    fn test_response_of() {
+        assert_eq!(response_of(42), "42");
+        assert_eq!(response_of(true), "true");
+        assert_eq!(response_of(()), "");
+        assert_eq!(
+            response_of(Err::<i64, _>("bad")),
+            error_response("HANDLER", "bad")
+        );
+        assert_eq!(
+            response_of("a\0b"),
+            error_response(
+                "HANDLER",
+                "invalid return value: string contains a NUL byte at position 1"
+            )
+        );
+    }
+
+    #[test]
+    fn test_error_response() {
+        assert_eq!(
+            error_response("HANDLER", "bad \"input\"\n"),
+            "\u{1}webui-rs:error:{\"code\":\"HANDLER\",\"message\":\"bad \\\"input\\\"\\n\"}"
+        );
+    }
+}
@@ -2,7 +2,18 @@
 #![allow(non_upper_case_globals)]
 
 pub mod bindgen;
+pub mod builder;
+pub mod client;
+mod convert;
+pub mod cookies;
+pub mod error;
 pub mod events;
+pub mod executor;
+#[cfg(feature = "macros")]
+pub mod export;
+pub mod handler;
+#[doc(hidden)]
+pub mod script;
 pub mod webui;
 pub mod window;
 
@@ -10,7 +21,14 @@ pub mod window;
 // pub use bindgen::*;
 // pub use events::*;
 // pub use webui::*;
-pub use window::Window;
+pub use builder::WindowBuilder;
+pub use client::{Client, ClientId};
+pub use cookies::{Cookie, CookieJar};
+pub use error::{ScriptError, ScriptErrorKind, WebUiError};
+#[cfg(feature = "serde")]
+pub use script::JsArgs;
+pub use script::ScriptExt;
+pub use window::{Window, WindowRef};
 
 use bindgen::{webui_browser, webui_config, webui_runtime};
 use std::ffi::CStr;
@@ -23,11 +41,30 @@ pub type Runtime = webui_runtime;
 pub type Config = webui_config;
 
 fn char_to_string(c: *const i8) -> String {
+    if c.is_null() {
+        return String::new();
+    }
+
     let cstr = unsafe { CStr::from_ptr(c) };
     let s: String = String::from_utf8_lossy(cstr.to_bytes()).to_string();
     s
 }
 
+/// Formats a script, encoding every argument as a JavaScript literal so that
+/// Rust values are interpolated safely. Returns `Result<String, WebUiError>`.
+/// Only positional `{}` placeholders are expanded, `{{` and `}}` are braces.
+///
+/// ```ignore
+/// win.run(&webui::js!("chart.setData({}, {})", points, options)?)?;
+/// ```
+#[cfg(feature = "serde")]
+#[macro_export]
+macro_rules! js {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::script::format_js($fmt, &[$($crate::script::to_js(&$arg)),*])
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -36,7 +73,7 @@ mod tests {
     fn test_webui_window() {
         let win = window::Window::new();
         assert_eq!(win.id, 1);
-        win.show("<span>Hello World</span>");
+        let _ = win.show("<span>Hello World</span>");
 
         // Wait 2 seconds, then kill
         std::thread::sleep(std::time::Duration::from_secs(2));
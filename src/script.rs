@@ -0,0 +1,534 @@
+// Helpers shared by the script methods of `Window`, `Event` and `Client`
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{ScriptError, ScriptErrorKind, WebUiError};
+use crate::executor::spawn_blocking;
+
+// Buffer tried first by `script_sized`, larger results take a second call
+const SIZED_BUFFER_LENGTH: usize = 8 * 1024;
+
+// Keys of the results kept in the page until they are fetched
+static NEXT_RESULT_KEY: AtomicU64 = AtomicU64::new(1);
+
+// Run `body`, which must set the string `text`, with `script`, which runs a
+// script with a given buffer length. A text too long for the first buffer is
+// kept in the page and fetched with a buffer of its size, so `body` runs once.
+pub(crate) fn script_sized<S>(script: S, body: &str) -> Result<String, WebUiError>
+where
+    S: Fn(&str, usize) -> Result<String, WebUiError>,
+{
+    let key = NEXT_RESULT_KEY.fetch_add(1, Ordering::Relaxed);
+    let response = script(
+        &sized_script(body, key, SIZED_BUFFER_LENGTH),
+        SIZED_BUFFER_LENGTH,
+    )?;
+
+    // The response is `<length>:<text>`, without the text if it did not fit
+    let (length, text) = response
+        .split_once(':')
+        .and_then(|(length, text)| Some((length.parse::<usize>().ok()?, text)))
+        .ok_or_else(|| {
+            let message = format!("unexpected response {:?}", response);
+            ScriptError::new(ScriptErrorKind::Internal, &message)
+        })?;
+
+    // A text of another length was cut at a NUL character, by webui
+    let cut = || {
+        let message = format!("result of {} bytes was cut at a NUL character", length);
+        ScriptError::new(ScriptErrorKind::Internal, &message).into()
+    };
+    if text.len() == length {
+        return Ok(text.to_owned());
+    }
+    if !text.is_empty() {
+        return Err(cut());
+    }
+
+    let text = script(&fetch_script(key), length + 2)?;
+    match text.len() == length {
+        true => Ok(text),
+        false => Err(cut()),
+    }
+}
+
+// Marks an exception caught by the wrapper of `catch_exceptions`
+const SCRIPT_ERROR_PREFIX: &str = "\u{1}webui-rs:script-error:";
+
+// Turn the buffer filled by `webui_script` into a result
+pub(crate) fn script_result(success: bool, buffer: Vec<u8>) -> Result<String, WebUiError> {
+    if buffer.is_empty() {
+        return Err(ScriptError::new(ScriptErrorKind::Truncated, "").into());
+    }
+
+    let length = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    let text = String::from_utf8_lossy(&buffer[..length]).into_owned();
+
+    // On failure the buffer holds the JavaScript error, if any
+    if !success {
+        return match text.is_empty() {
+            true => Err(ScriptError::new(ScriptErrorKind::Timeout, "").into()),
+            false => Err(ScriptError::new(ScriptErrorKind::Exception, &text).into()),
+        };
+    }
+
+    // A result filling the whole buffer may have been cut, so one of exactly
+    // `buffer_length - 1` bytes is reported as possibly truncated too
+    if length > 0 && length + 1 >= buffer.len() {
+        return Err(ScriptError::new(ScriptErrorKind::Truncated, "").into());
+    }
+
+    // The message and the stack of a caught exception
+    if let Some(error) = text.strip_prefix(SCRIPT_ERROR_PREFIX) {
+        let (message, stack) = error.split_once('\u{1}').unwrap_or((error, ""));
+        return Err(ScriptError {
+            kind: ScriptErrorKind::Exception,
+            message: message.to_owned(),
+            stack: (!stack.is_empty()).then(|| stack.to_owned()),
+        }
+        .into());
+    }
+
+    Ok(text)
+}
+
+// Quote a string as a JSON (and JavaScript) string literal
+pub(crate) fn json_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            // Valid in JSON but not in older JavaScript string literals
+            '\u{2028}' | '\u{2029}' => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Wrap a script so that an exception is returned as its message and stack,
+// read back by `script_result`
+pub(crate) fn catch_exceptions(script: &str) -> String {
+    format!(
+        r#"try {{
+{script}
+}} catch (e) {{
+  const message = String(e instanceof Error ? e.message : e).replaceAll("\u0001", "");
+  const stack = e instanceof Error && e.stack ? String(e.stack) : "";
+  return {prefix} + message + "\u0001" + stack;
+}}"#,
+        script = script,
+        prefix = json_quote(SCRIPT_ERROR_PREFIX),
+    )
+}
+
+// Panic of the worker running `script_async`
+fn worker_panicked() -> WebUiError {
+    ScriptError::new(ScriptErrorKind::Internal, "script worker panicked").into()
+}
+
+// Run the function body `js`, sized to fit its result
+pub(crate) fn script_body<S>(script: S, js: &str) -> Result<String, WebUiError>
+where
+    S: Fn(&str, usize) -> Result<String, WebUiError>,
+{
+    script_sized(
+        script,
+        &format!("const text = String((() => {{\n{}\n}})());", js),
+    )
+}
+
+// Evaluate the expression `js` as JSON and deserialize it
+#[cfg(feature = "serde")]
+pub(crate) fn script_typed<T, S>(script: S, js: &str) -> Result<T, WebUiError>
+where
+    T: serde::de::DeserializeOwned,
+    S: Fn(&str, usize) -> Result<String, WebUiError>,
+{
+    let body = format!("const text = JSON.stringify(({})) ?? \"null\";", js);
+    let json = script_sized(script, &body)?;
+
+    serde_json::from_str(&json).map_err(|err| WebUiError::InvalidJson(err.to_string()))
+}
+
+/// Encodes a value as a JavaScript literal, for `js!`.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub fn to_js<T: serde::Serialize + ?Sized>(value: &T) -> Result<String, WebUiError> {
+    let json =
+        serde_json::to_string(value).map_err(|err| WebUiError::InvalidJson(err.to_string()))?;
+
+    // Valid in JSON but not in older JavaScript string literals
+    Ok(json
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029"))
+}
+
+// A dotted path of JavaScript identifiers, e.g. `app.chart.update`
+#[cfg(feature = "serde")]
+fn is_js_path(name: &str) -> bool {
+    name.split('.').all(|part| {
+        let mut chars = part.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    })
+}
+
+/// Arguments of a JavaScript call, see `WindowRef::call_js`: `()` or a tuple
+/// of `Serialize` values, each one passed as one argument.
+#[cfg(feature = "serde")]
+pub trait JsArgs {
+    fn to_js_args(&self) -> Result<Vec<String>, WebUiError>;
+}
+
+#[cfg(feature = "serde")]
+macro_rules! impl_js_args {
+    ($($arg:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($arg: serde::Serialize),*> JsArgs for ($($arg,)*) {
+            fn to_js_args(&self) -> Result<Vec<String>, WebUiError> {
+                let ($($arg,)*) = self;
+                Ok(vec![$(to_js($arg)?),*])
+            }
+        }
+    };
+}
+
+#[cfg(feature = "serde")]
+mod js_args {
+    use super::*;
+
+    impl_js_args!();
+    impl_js_args!(A1);
+    impl_js_args!(A1, A2);
+    impl_js_args!(A1, A2, A3);
+    impl_js_args!(A1, A2, A3, A4);
+    impl_js_args!(A1, A2, A3, A4, A5);
+    impl_js_args!(A1, A2, A3, A4, A5, A6);
+    impl_js_args!(A1, A2, A3, A4, A5, A6, A7);
+    impl_js_args!(A1, A2, A3, A4, A5, A6, A7, A8);
+}
+
+// The expression calling `name` with `args`
+#[cfg(feature = "serde")]
+pub(crate) fn call_expression<A: JsArgs>(name: &str, args: &A) -> Result<String, WebUiError> {
+    if !is_js_path(name) {
+        return Err(WebUiError::InvalidIdentifier(name.to_owned()));
+    }
+
+    Ok(format!("{}({})", name, args.to_js_args()?.join(", ")))
+}
+
+mod sealed {
+    use crate::error::WebUiError;
+
+    // What can run scripts, implemented in this crate only
+    pub trait ScriptTarget {
+        fn run(&self, script: &str) -> Result<(), WebUiError>;
+        fn script(
+            &self,
+            script: &str,
+            timeout: usize,
+            buffer_length: usize,
+        ) -> Result<String, WebUiError>;
+    }
+}
+
+use sealed::ScriptTarget;
+
+macro_rules! impl_script_target {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::ScriptTarget for $ty {
+                fn run(&self, script: &str) -> Result<(), WebUiError> {
+                    <$ty>::run(self, script)
+                }
+
+                fn script(
+                    &self,
+                    script: &str,
+                    timeout: usize,
+                    buffer_length: usize,
+                ) -> Result<String, WebUiError> {
+                    <$ty>::script(self, script, timeout, buffer_length)
+                }
+            }
+        )*
+    };
+}
+
+impl_script_target!(
+    crate::window::WindowRef,
+    crate::events::Event<'_>,
+    crate::client::Client
+);
+
+/// Sized, async and typed scripts and JavaScript calls, on `WindowRef`,
+/// `Event` and `Client`.
+pub trait ScriptExt: sealed::ScriptTarget {
+    // Like `script` with a buffer sized to the result, run on a worker
+    // thread so that async code can await it without blocking its executor
+    fn script_async(
+        &self,
+        js: &str,
+        timeout: usize,
+    ) -> impl Future<Output = Result<String, WebUiError>> + Send + 'static
+    where
+        Self: Copy + Send + 'static,
+    {
+        let target = *self;
+        let js = js.to_owned();
+
+        async move {
+            let script = move || {
+                script_body(
+                    |script, buffer_length| {
+                        ScriptTarget::script(&target, script, timeout, buffer_length)
+                    },
+                    &js,
+                )
+            };
+            spawn_blocking(script)
+                .await
+                .unwrap_or_else(|| Err(worker_panicked()))
+        }
+    }
+
+    // Evaluate the JavaScript expression `js` and deserialize its value,
+    // sized to fit however long the result is
+    #[cfg(feature = "serde")]
+    fn script_typed<T>(&self, js: &str, timeout: usize) -> Result<T, WebUiError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        script_typed(
+            |script, buffer_length| ScriptTarget::script(self, script, timeout, buffer_length),
+            js,
+        )
+    }
+
+    // Call the JavaScript function `name` with `args` encoded as JSON, one
+    // argument per tuple field, e.g. `("title", [1, 2])` or `()`
+    #[cfg(feature = "serde")]
+    fn call_js<A: JsArgs>(&self, name: &str, args: &A) -> Result<(), WebUiError> {
+        ScriptTarget::run(self, &format!("{};", call_expression(name, args)?))
+    }
+
+    // Like `call_js`, returning the deserialized result of the function
+    #[cfg(feature = "serde")]
+    fn call_js_typed<T, A>(&self, name: &str, args: &A, timeout: usize) -> Result<T, WebUiError>
+    where
+        T: serde::de::DeserializeOwned,
+        A: JsArgs,
+    {
+        self.script_typed(&call_expression(name, args)?, timeout)
+    }
+}
+
+impl<S: sealed::ScriptTarget> ScriptExt for S {}
+
+/// Replaces the `{}` placeholders of `template` with `args`, for `js!`.
+/// `{{` and `}}` are literal braces, anything else in braces is rejected.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub fn format_js(
+    template: &str,
+    args: &[Result<String, WebUiError>],
+) -> Result<String, WebUiError> {
+    let invalid = |reason: &str| Err(WebUiError::InvalidTemplate(reason.to_owned()));
+    let mut args = args.iter();
+    let mut script = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+                match args.next() {
+                    Some(arg) => script.push_str(arg.as_ref().map_err(Clone::clone)?),
+                    None => return invalid("more placeholders than arguments"),
+                }
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                script.push(c);
+            }
+            ('{', _) | ('}', _) => return invalid("only `{}` placeholders are supported"),
+            (c, _) => script.push(c),
+        }
+    }
+
+    match args.next() {
+        Some(_) => invalid("more arguments than placeholders"),
+        None => Ok(script),
+    }
+}
+
+fn sized_script(body: &str, key: u64, buffer_length: usize) -> String {
+    format!(
+        r#"{body}
+const length = new TextEncoder().encode(text).length;
+if (String(length).length + 1 + length < {limit}) return length + ":" + text;
+(globalThis.__webuiRsResults ??= {{}})[{key}] = text;
+return length + ":";"#,
+        body = body,
+        limit = buffer_length - 1,
+        key = key,
+    )
+}
+
+fn fetch_script(key: u64) -> String {
+    format!(
+        "const text = globalThis.__webuiRsResults[{key}];\n\
+         delete globalThis.__webuiRsResults[{key}];\n\
+         return text;",
+        key = key
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_result() {
+        let buffer = |s: &str, len: usize| {
+            let mut buffer = s.as_bytes().to_vec();
+            buffer.resize(len, 0);
+            buffer
+        };
+
+        assert_eq!(script_result(true, buffer("42", 8)), Ok("42".to_owned()));
+        assert_eq!(script_result(true, buffer("", 1)), Ok(String::new()));
+        let kind = |result: Result<String, WebUiError>| match result {
+            Err(WebUiError::ScriptError(err)) => Some(err.kind),
+            _ => None,
+        };
+        assert_eq!(
+            kind(script_result(true, buffer("1234567", 8))),
+            Some(ScriptErrorKind::Truncated)
+        );
+        assert_eq!(
+            kind(script_result(false, buffer("", 8))),
+            Some(ScriptErrorKind::Timeout)
+        );
+        assert_eq!(
+            kind(script_result(false, buffer("oops", 8))),
+            Some(ScriptErrorKind::Exception)
+        );
+
+        let thrown = format!("{}boom\u{1}Error: boom\n    at f", SCRIPT_ERROR_PREFIX);
+        assert_eq!(
+            script_result(true, buffer(&thrown, 64)),
+            Err(WebUiError::ScriptError(ScriptError {
+                kind: ScriptErrorKind::Exception,
+                message: "boom".to_owned(),
+                stack: Some("Error: boom\n    at f".to_owned()),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_script_sized_rejects_cut_results() {
+        assert_eq!(
+            script_sized(|_, _| Ok("2:ab".to_owned()), ""),
+            Ok("ab".to_owned())
+        );
+        assert_eq!(
+            script_sized(|_, _| Ok("0:".to_owned()), ""),
+            Ok(String::new())
+        );
+
+        // `a\0b` as webui returns it, with or without a fetch
+        assert!(script_sized(|_, _| Ok("3:a".to_owned()), "").is_err());
+        let script = |script: &str, _| match script.starts_with("const text = globalThis") {
+            true => Ok("a".to_owned()),
+            false => Ok("3:".to_owned()),
+        };
+        assert!(script_sized(script, "").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_script_typed_fetches_long_results() {
+        let long = "x".repeat(SIZED_BUFFER_LENGTH * 2);
+        let json = serde_json::to_string(&long).unwrap();
+        let calls = std::cell::RefCell::new(Vec::new());
+
+        // Stands in for the page, answering like `sized_script` would
+        let script = |script: &str, buffer_length: usize| {
+            calls.borrow_mut().push(buffer_length);
+            match script.starts_with("const text = JSON") {
+                true => Ok(format!("{}:", json.len())),
+                false => Ok(json.clone()),
+            }
+        };
+
+        let value: String = script_typed(script, "document.title").unwrap();
+        assert_eq!(value, long);
+        assert_eq!(*calls.borrow(), vec![SIZED_BUFFER_LENGTH, json.len() + 2]);
+
+        let value: Vec<i32> = script_typed(|_, _| Ok("7:[1,2,3]".to_owned()), "[1, 2, 3]").unwrap();
+        assert_eq!(value, vec![1, 2, 3]);
+        assert!(script_typed::<i32, _>(|_, _| Ok("oops".to_owned()), "1").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_call_expression() {
+        assert_eq!(
+            call_expression("app.updateChart", &("a\"b", [1, 2], true)).unwrap(),
+            r#"app.updateChart("a\"b", [1,2], true)"#
+        );
+        assert_eq!(call_expression("refresh", &()).unwrap(), "refresh()");
+        assert_eq!(call_expression("$set", &("x",)).unwrap(), r#"$set("x")"#);
+
+        // One array or `None` stays one argument
+        assert_eq!(
+            call_expression("updateChart", &(vec![1, 2, 3],)).unwrap(),
+            "updateChart([1,2,3])"
+        );
+        assert_eq!(
+            call_expression("select", &(None::<i32>,)).unwrap(),
+            "select(null)"
+        );
+        assert_eq!(
+            call_expression("alert(1);f", &()),
+            Err(WebUiError::InvalidIdentifier("alert(1);f".to_owned()))
+        );
+        assert!(call_expression("a..b", &()).is_err());
+        assert!(call_expression("1st", &()).is_err());
+
+        let name = "</script>\u{2028}";
+        assert_eq!(
+            crate::js!("greet({}, {})", name, 42).unwrap(),
+            "greet(\"</script>\\u2028\", 42)"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_js_only_expands_positional_placeholders() {
+        assert_eq!(
+            crate::js!("if (x) {{ f({}); }}", [1]).unwrap(),
+            "if (x) { f([1]); }"
+        );
+        assert_eq!(crate::js!("refresh()").unwrap(), "refresh()");
+
+        // An inline capture such as `{name}` would bypass the encoding
+        assert!(crate::js!("greet(\"{name}\")").is_err());
+        assert!(crate::js!("f({0})", 1).is_err());
+        assert!(crate::js!("f({}, {})", 1).is_err());
+        assert!(crate::js!("f({})", 1, 2).is_err());
+    }
+}
@@ -16,86 +16,206 @@
 
 // Modules
 use crate::char_to_string;
+use crate::error::WebUiError;
 use crate::events::EventSimple;
 use crate::events::EventType;
+use crate::executor::Executor;
+use crate::handler::{catch_panic, error_response, HandlerPanic};
+use crate::window::{start_close_monitor, WindowRef};
 use crate::Browser;
 use crate::Config;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::CString;
 use std::os::raw::c_char;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::sync::LazyLock;
 use std::sync::Mutex;
+use std::sync::{Condvar, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::bindgen::*;
 
-const WINDOWS: usize = 64;
-const ELEMENTS: usize = 64;
+#[cfg(feature = "macros")]
+pub use crate::export::{exports, js_bindings, register_all};
+#[cfg(feature = "macros")]
+pub use webui_rs_macros::export;
 
-pub struct BindStore<T> {
-    func_store: Mutex<[[Option<T>; ELEMENTS]; WINDOWS]>,
-    elements_map: Mutex<HashMap<String, usize>>,
+/// Callback type stored for `interface_bind`.
+pub type InterfaceHandler = dyn Fn(EventSimple) + Send + Sync + 'static;
+
+struct Binding<T: ?Sized> {
+    bind_id: usize,
+    token: u64,
+    func: Arc<T>,
+}
+
+// Handlers per window, then per element. Both levels grow as needed.
+pub struct BindStore<T: ?Sized> {
+    windows: Mutex<HashMap<usize, HashMap<String, Binding<T>>>>,
+    next_token: AtomicU64,
+}
+
+impl<T: ?Sized> Default for BindStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<T: Copy> BindStore<T> {
+impl<T: ?Sized> BindStore<T> {
     pub fn new() -> BindStore<T> {
         BindStore {
-            func_store: Mutex::new([[None; ELEMENTS]; WINDOWS]),
-            elements_map: Mutex::new(HashMap::new()),
+            windows: Mutex::new(HashMap::new()),
+            next_token: AtomicU64::new(1),
         }
     }
 
-    fn save_string(&self, s: &str) -> usize {
-        let mut map = self.elements_map.lock().unwrap();
-        // Check if the string already exists in the map
-        if let Some(&index) = map.get(s) {
-            return index;
+    /// Stores `func`, replacing any handler already bound to `element`.
+    /// Returns a token identifying this particular registration.
+    pub fn add_function(&self, window: usize, element: &str, bind_id: usize, func: Arc<T>) -> u64 {
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        self.windows
+            .lock()
+            .unwrap()
+            .entry(window)
+            .or_default()
+            .insert(
+                element.to_owned(),
+                Binding {
+                    bind_id,
+                    token,
+                    func,
+                },
+            );
+        token
+    }
+
+    /// Removes the handler bound to `element`. With a token, only that
+    /// registration is removed, so a stale handle cannot drop a rebind.
+    pub fn remove_function(&self, window: usize, element: &str, token: Option<u64>) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        let Some(bindings) = windows.get_mut(&window) else {
+            return false;
+        };
+
+        match bindings.get(element) {
+            Some(binding) if token.is_none_or(|token| token == binding.token) => {
+                bindings.remove(element);
+                true
+            }
+            _ => false,
         }
+    }
 
-        // If the string does not exist, add it to the map and return the new index
-        let index = map.len();
-        map.insert(s.to_owned(), index);
-        index
+    pub fn elements(&self, window: usize) -> Vec<String> {
+        let windows = self.windows.lock().unwrap();
+        let mut elements: Vec<String> = windows
+            .get(&window)
+            .map(|bindings| bindings.keys().cloned().collect())
+            .unwrap_or_default();
+        elements.sort();
+        elements
     }
 
-    fn find_string(&self, s: &str) -> isize {
-        let map = self.elements_map.lock().unwrap();
-        if let Some(&index) = map.get(s) {
-            index as isize
-        } else {
-            -1
-        }
+    /// Returns a clone of the stored handler, so the store is not locked
+    /// while the handler runs (handlers may bind or unbind themselves).
+    pub fn get_function(&self, window: usize, element: &str, bind_id: usize) -> Option<Arc<T>> {
+        let windows = self.windows.lock().unwrap();
+        let bindings = windows.get(&window)?;
+
+        // The empty element receives every event whatever the element is,
+        // so the bind id tells which of the two bindings webui is calling.
+        // An event of `element` on another binding belongs to neither.
+        let binding = [bindings.get(""), bindings.get(element)]
+            .into_iter()
+            .flatten()
+            .find(|binding| binding.bind_id == bind_id);
+        binding.map(|binding| binding.func.clone())
     }
 
-    pub fn add_function(&self, window: usize, element: &str, func: T) {
-        let element_index = self.save_string(element);
-        self.func_store.lock().unwrap()[window][element_index] = Some(func);
+    /// Drops every handler bound to `window`.
+    pub fn remove_window(&self, window: usize) {
+        self.windows.lock().unwrap().remove(&window);
     }
+}
+
+static BIND_STORE_SIMPLE: LazyLock<BindStore<InterfaceHandler>> = LazyLock::new(BindStore::new);
 
-    pub fn get_function(&self, window: usize, element: &str) -> Option<T> {
-        let element_index = self.find_string(element);
-        if element_index < 0 {
-            return None;
+// Live windows, with whether each one has been shown yet
+static WINDOWS: LazyLock<Mutex<BTreeMap<usize, bool>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+// Whether `Config::multi_client` was enabled, to track new windows' clients
+static MULTI_CLIENT: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn track_window(id: usize) {
+    if id != 0 {
+        WINDOWS.lock().unwrap().insert(id, false);
+        if MULTI_CLIENT.load(Ordering::SeqCst) {
+            WindowRef::from_id(id).track_clients();
         }
-        self.func_store.lock().unwrap()[window][element_index as usize]
     }
 }
 
-static mut BIND_STORE_SIMPLE: LazyLock<BindStore<fn(EventSimple)>> =
-    LazyLock::new(|| BindStore::new());
+pub(crate) fn untrack_window(id: usize) {
+    WINDOWS.lock().unwrap().remove(&id);
+}
+
+// Also lists again a window shown after being pruned
+pub(crate) fn mark_window_shown(id: usize) {
+    WINDOWS.lock().unwrap().insert(id, true);
+}
+
+// Forget the windows that were shown and have been closed since
+fn prune_windows(windows: &mut BTreeMap<usize, bool>) {
+    windows.retain(|&id, &mut shown| !shown || unsafe { webui_is_shown(id) });
+}
+
+/// Every live window, in id order. Windows that have been shown then closed
+/// are no longer listed.
+pub fn windows() -> Vec<WindowRef> {
+    let mut windows = WINDOWS.lock().unwrap();
+    prune_windows(&mut windows);
+    windows.keys().map(|&id| WindowRef::from_id(id)).collect()
+}
+
+pub fn window(id: usize) -> Option<WindowRef> {
+    let mut windows = WINDOWS.lock().unwrap();
+    prune_windows(&mut windows);
+    windows.get(&id).map(|_| WindowRef::from_id(id))
+}
+
+/// Runs `script` in every live window, without waiting for a result.
+pub fn broadcast_run(script: &str) -> Result<(), WebUiError> {
+    let script_c_str = CString::new(script)?;
+    let script_c_char: *const c_char = script_c_str.as_ptr() as *const c_char;
+
+    for win in windows() {
+        unsafe {
+            webui_run(win.id, script_c_char);
+        }
+    }
+    Ok(())
+}
 
 // Function Implementations
 pub fn new_window() -> usize {
-    unsafe {
+    let id = unsafe {
         // GLOBAL_ARRAY = [[GlobalArray::None; COLS]; ROWS];
         webui_new_window()
-    }
+    };
+    track_window(id);
+    id
 }
 
 pub fn new_window_id(id: usize) -> usize {
-    unsafe {
+    let id = unsafe {
         // GLOBAL_ARRAY = [[GlobalArray::None; COLS]; ROWS];
         webui_new_window_id(id)
-    }
+    };
+    track_window(id);
+    id
 }
 
 pub fn get_new_window_id() -> usize {
@@ -106,30 +226,133 @@ pub fn is_high_contrast() -> bool {
     unsafe { webui_is_high_contrast() }
 }
 
-pub fn browser_exist(browser: Browser) {
-    unsafe {
-        webui_browser_exist(browser as usize);
-    }
+pub fn browser_exist(browser: Browser) -> bool {
+    unsafe { webui_browser_exist(browser as usize) }
+}
+
+/// Every browser found on this machine. The `AnyBrowser` and `ChromiumBased`
+/// selections are not listed.
+pub fn installed_browsers() -> Vec<Browser> {
+    (0..)
+        .map_while(|value| Browser::try_from(value).ok())
+        .filter(|browser| {
+            !matches!(
+                browser,
+                Browser::NoBrowser | Browser::AnyBrowser | Browser::ChromiumBased
+            )
+        })
+        .filter(|&browser| browser_exist(browser))
+        .collect()
 }
 
 pub fn wait() {
     unsafe {
         webui_wait();
     }
+
+    // Keep blocking after the last window closed, until `exit`
+    if exit_policy() != ExitPolicy::ExitWhenLastWindowCloses {
+        let mut exited = EXITED.lock().unwrap();
+        while !*exited {
+            exited = EXIT_SIGNAL.wait(exited).unwrap();
+        }
+    }
+}
+
+/// What the app does when its browser windows are closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExitPolicy {
+    // `wait` returns once every window is closed
+    #[default]
+    ExitWhenLastWindowCloses,
+    // `wait` keeps blocking until `exit`, e.g. for a background service
+    KeepRunning,
+    // A window closed by the user is shown again with the same content,
+    // `wait` blocks until `exit`
+    ReopenOnClose,
+}
+
+static EXIT_POLICY: RwLock<ExitPolicy> = RwLock::new(ExitPolicy::ExitWhenLastWindowCloses);
+
+static EXITED: Mutex<bool> = Mutex::new(false);
+static EXIT_SIGNAL: Condvar = Condvar::new();
+
+pub fn set_exit_policy(policy: ExitPolicy) {
+    *EXIT_POLICY.write().unwrap() = policy;
+    if policy == ExitPolicy::ReopenOnClose {
+        start_close_monitor();
+    }
+}
+
+pub fn exit_policy() -> ExitPolicy {
+    *EXIT_POLICY.read().unwrap()
+}
+
+pub(crate) fn exit_requested() -> bool {
+    *EXITED.lock().unwrap()
+}
+
+/// Returned by `wait_timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStatus {
+    // Every window is closed, or `exit` was called
+    Finished,
+    TimedOut,
+}
+
+// How often the waits below check the state of webui
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Whether webui is running, i.e. `wait` would still block.
+pub fn is_running() -> bool {
+    match exit_policy() {
+        ExitPolicy::ExitWhenLastWindowCloses => unsafe { webui_interface_is_app_running() },
+        _ => !exit_requested(),
+    }
+}
+
+/// Like `wait`, but gives up after `timeout`. Calling it again resumes the wait.
+pub fn wait_timeout(timeout: Duration) -> WaitStatus {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if !is_running() {
+            return WaitStatus::Finished;
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return WaitStatus::TimedOut;
+        }
+        thread::sleep(WAIT_POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Blocks until `win` is closed or webui exits. Returns at once for a window
+/// that is not shown.
+pub fn wait_for(win: &WindowRef) {
+    while win.is_shown() && is_running() {
+        thread::sleep(WAIT_POLL_INTERVAL);
+    }
 }
 
 pub fn exit() {
+    *EXITED.lock().unwrap() = true;
+    EXIT_SIGNAL.notify_all();
+
     unsafe {
         webui_exit();
     }
 }
 
-pub fn set_default_root_folder(folder: &str) {
-    let folder_c_str = CString::new(folder).unwrap();
+pub fn set_default_root_folder(folder: &str) -> Result<(), WebUiError> {
+    let folder_c_str = CString::new(folder)?;
     let folder_c_char: *const c_char = folder_c_str.as_ptr() as *const c_char;
 
     unsafe {
-        webui_set_default_root_folder(folder_c_char);
+        match webui_set_default_root_folder(folder_c_char) {
+            true => Ok(()),
+            false => Err(WebUiError::RootFolderRejected(folder.to_owned())),
+        }
     }
 }
 
@@ -139,27 +362,35 @@ pub fn set_timeout(seconds: usize) {
     }
 }
 
-pub fn encode(data: &str) -> String {
-    let data_c_str = CString::new(data).unwrap();
+pub fn encode(data: &str) -> Result<String, WebUiError> {
+    let data_c_str = CString::new(data)?;
     let data_c_char: *const c_char = data_c_str.as_ptr() as *const c_char;
 
-    unsafe {
-        let encoded = webui_encode(data_c_char);
-        char_to_string(encoded)
-    }
+    unsafe { take_string(webui_encode(data_c_char)) }
 }
 
-pub fn decode(data: &str) -> String {
-    let data_c_str = CString::new(data).unwrap();
+pub fn decode(data: &str) -> Result<String, WebUiError> {
+    let data_c_str = CString::new(data)?;
     let data_c_char: *const c_char = data_c_str.as_ptr() as *const c_char;
 
-    unsafe {
-        let decoded = webui_decode(data_c_char);
-        char_to_string(decoded)
+    unsafe { take_string(webui_decode(data_c_char)) }
+}
+
+// Copy a string allocated by webui, then free it
+unsafe fn take_string(data: *mut c_char) -> Result<String, WebUiError> {
+    if data.is_null() {
+        return Err(WebUiError::EncodingFailed);
     }
+
+    let string = char_to_string(data);
+    webui_free(data as *mut std::os::raw::c_void);
+    Ok(string)
 }
 
-pub fn free(data: *mut std::os::raw::c_void) {
+/// # Safety
+///
+/// `data` must have been allocated by webui and not freed yet.
+pub unsafe fn free(data: *mut std::os::raw::c_void) {
     unsafe {
         webui_free(data);
     }
@@ -169,13 +400,14 @@ pub fn malloc(size: usize) -> *mut std::os::raw::c_void {
     unsafe { webui_malloc(size) }
 }
 
-pub fn open_url(url: &str) {
-    let url_c_str = CString::new(url).unwrap();
+pub fn open_url(url: &str) -> Result<(), WebUiError> {
+    let url_c_str = CString::new(url)?;
     let url_c_char: *const c_char = url_c_str.as_ptr() as *const c_char;
 
     unsafe {
         webui_open_url(url_c_char);
     }
+    Ok(())
 }
 
 pub fn clean() {
@@ -194,29 +426,43 @@ pub fn get_free_port() -> usize {
     unsafe { webui_get_free_port() }
 }
 
+// Enabling `Config::multi_client` also tracks the clients of every window,
+// created before or after, see `WindowRef::track_clients`
 pub fn set_config(option: Config, enabled: bool) {
     unsafe {
         webui_set_config(option as webui_config, enabled);
     }
+
+    if option == Config::multi_client {
+        MULTI_CLIENT.store(enabled, Ordering::SeqCst);
+        if enabled {
+            windows().iter().for_each(WindowRef::track_clients);
+        }
+    }
 }
 
-pub fn get_mime_type(file: &str) -> String {
-    let file_c_str = CString::new(file).unwrap();
+pub fn get_mime_type(file: &str) -> Result<String, WebUiError> {
+    let file_c_str = CString::new(file)?;
     let file_c_char: *const c_char = file_c_str.as_ptr() as *const c_char;
 
     unsafe {
         let mime = webui_get_mime_type(file_c_char);
-        char_to_string(mime)
+        Ok(char_to_string(mime))
     }
 }
 
-pub fn set_tls_certificate(cert_pem: &str, key_pem: &str) -> bool {
-    let cert_pem_c_str = CString::new(cert_pem).unwrap();
-    let key_pem_c_str = CString::new(key_pem).unwrap();
+pub fn set_tls_certificate(cert_pem: &str, key_pem: &str) -> Result<(), WebUiError> {
+    let cert_pem_c_str = CString::new(cert_pem)?;
+    let key_pem_c_str = CString::new(key_pem)?;
     let cert_pem_c_char: *const c_char = cert_pem_c_str.as_ptr() as *const c_char;
     let key_pem_c_char: *const c_char = key_pem_c_str.as_ptr() as *const c_char;
 
-    unsafe { webui_set_tls_certificate(cert_pem_c_char, key_pem_c_char) }
+    unsafe {
+        match webui_set_tls_certificate(cert_pem_c_char, key_pem_c_char) {
+            true => Ok(()),
+            false => Err(WebUiError::TlsRejected),
+        }
+    }
 }
 
 unsafe extern "C" fn events_handler(
@@ -226,27 +472,58 @@ unsafe extern "C" fn events_handler(
     event_number: usize,
     bind_id: usize,
 ) {
-    // Call the Rust user function
-    unsafe {
+    let element = char_to_string(element);
+
+    // Call the Rust user function, a panic must not unwind into webui
+    let result = catch_panic(window, &element, || unsafe {
         let window_id = webui_interface_get_window_id(window);
 
-        if let Some(func) = BIND_STORE_SIMPLE.get_function(window_id, &char_to_string(element)) {
+        if let Some(func) = BIND_STORE_SIMPLE.get_function(window_id, &element, bind_id) {
             let evt = EventSimple {
                 win: window,
                 event_type: EventType::from_usize(event_type),
-                element: char_to_string(element),
+                element: element.clone(),
                 event_number,
                 bind_id,
             };
 
             func(evt);
         }
+    });
+
+    if let Err(panic) = result {
+        unsafe {
+            let response =
+                CString::new(error_response("PANIC", &panic.to_string())).unwrap_or_default();
+            webui_interface_set_response(window, event_number, response.as_ptr());
+        }
     }
 }
 
-pub fn interface_bind(win: usize, element: &str, func: fn(EventSimple)) -> usize {
+// Called with every panic caught in a handler, on top of the standard panic
+// output. The panic is also reported to the JavaScript caller.
+pub fn set_handler_panic_hook<F>(hook: F)
+where
+    F: Fn(&HandlerPanic) + Send + Sync + 'static,
+{
+    crate::handler::set_panic_hook(Some(Arc::new(hook)));
+}
+
+pub fn reset_handler_panic_hook() {
+    crate::handler::set_panic_hook(None);
+}
+
+// Use `executor` instead of the built-in thread pool for async handlers
+pub fn set_executor(executor: impl Executor) {
+    crate::executor::set_executor(Arc::new(executor));
+}
+
+pub fn interface_bind<F>(win: usize, element: &str, func: F) -> Result<usize, WebUiError>
+where
+    F: Fn(EventSimple) + Send + Sync + 'static,
+{
     // Element String to i8/u8
-    let element_c_str = CString::new(element).unwrap();
+    let element_c_str = CString::new(element)?;
     let element_c_char: *const c_char = element_c_str.as_ptr() as *const c_char;
 
     // Bind
@@ -257,13 +534,20 @@ pub fn interface_bind(win: usize, element: &str, func: fn(EventSimple)) -> usize
 
         let window_id = webui_interface_get_window_id(win);
 
+        let bind_id = webui_interface_bind(win, element_c_char, f);
+
         // Add the Rust user function to the list
-        BIND_STORE_SIMPLE.add_function(window_id, element, func);
+        BIND_STORE_SIMPLE.add_function(window_id, element, bind_id, Arc::new(func));
 
-        webui_interface_bind(win, element_c_char, f)
+        Ok(bind_id)
     }
 }
 
+// Drop the handlers registered through `interface_bind` for a window
+pub(crate) fn interface_unbind_all(window_id: usize) {
+    BIND_STORE_SIMPLE.remove_window(window_id);
+}
+
 pub fn interface_is_app_running() -> bool {
     unsafe { webui_interface_is_app_running() }
 }
@@ -271,3 +555,57 @@ pub fn interface_is_app_running() -> bool {
 pub fn interface_get_window_id(win: usize) -> usize {
     unsafe { webui_interface_get_window_id(win) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_store_has_no_limits() {
+        let store: BindStore<dyn Fn() -> usize + Send + Sync> = BindStore::new();
+
+        for window in [1, 64, 500] {
+            for i in 0..200 {
+                store.add_function(
+                    window,
+                    &format!("element_{}", i),
+                    i + 1,
+                    Arc::new(move || i),
+                );
+            }
+        }
+
+        let func = store.get_function(500, "element_150", 151).unwrap();
+        assert_eq!(func(), 150);
+        assert!(store.get_function(2, "element_0", 1).is_none());
+
+        // A stale token must not remove a newer registration
+        let old = store.add_function(1, "rebind", 1, Arc::new(|| 1));
+        let new = store.add_function(1, "rebind", 1, Arc::new(|| 2));
+        assert!(!store.remove_function(1, "rebind", Some(old)));
+        assert!(store.remove_function(1, "rebind", Some(new)));
+        assert!(!store.elements(1).contains(&"rebind".to_owned()));
+
+        store.remove_window(64);
+        assert!(store.get_function(64, "element_0", 1).is_none());
+        assert!(store.get_function(1, "element_0", 1).is_some());
+
+        // An event reported to the empty element binding only reaches `""`
+        assert!(store.get_function(1, "element_0", 999).is_none());
+        store.add_function(1, "", 999, Arc::new(|| 1000));
+        assert_eq!(store.get_function(1, "element_0", 999).unwrap()(), 1000);
+        assert_eq!(store.get_function(1, "element_0", 1).unwrap()(), 0);
+    }
+
+    #[test]
+    fn test_window_registry() {
+        track_window(900);
+        track_window(0);
+        assert_eq!(window(900), Some(WindowRef::from_id(900)));
+        assert!(window(0).is_none());
+        assert!(windows().contains(&WindowRef::from_id(900)));
+
+        untrack_window(900);
+        assert!(window(900).is_none());
+    }
+}
@@ -5,39 +5,192 @@
 #![allow(non_upper_case_globals)]
 
 // Modules
+use crate::builder::WindowBuilder;
 use crate::char_to_string;
-use crate::events::Event;
+use crate::client::{self, Client, ClientId};
+use crate::error::{ScriptError, ScriptErrorKind, WebUiError};
+use crate::events::{Event, EventType};
+use crate::executor::executor;
+use crate::handler::{
+    catch_panic, return_error, with_client_shim, CatchPanic, Handler, IntoEventReturn, PendingCall,
+    CLIENT_SHIM,
+};
+use crate::script::script_result;
 use crate::webui::BindStore;
 use crate::webui::*;
 use crate::Browser;
 use crate::Runtime;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
+use std::future::Future;
 use std::os::raw::c_char;
+use std::sync::Arc;
 use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
 
 use crate::bindgen::*;
 
-const WINDOWS: usize = 64;
-const ELEMENTS: usize = 64;
-static mut BIND_STORE: LazyLock<BindStore<fn(Event)>> = LazyLock::new(|| BindStore::new());
+/// Callback type stored for `Window::bind`.
+pub type BindHandler = dyn Fn(Event) + Send + Sync + 'static;
 
+/// Callback type stored for the lifecycle helpers such as `Window::on_connect`.
+pub type LifecycleHandler = dyn Fn(&Event) + Send + Sync + 'static;
+
+static BIND_STORE: LazyLock<BindStore<BindHandler>> = LazyLock::new(BindStore::new);
+
+// Lifecycle handlers of a window, with the event type each one listens to,
+// and the bind id of the empty element binding webui reports them to
+struct Lifecycle {
+    bind_id: usize,
+    handlers: Vec<(EventType, Arc<LifecycleHandler>)>,
+}
+
+static LIFECYCLE: LazyLock<Mutex<HashMap<usize, Lifecycle>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static SHIM_INSTALLED: LazyLock<Mutex<HashSet<usize>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Callback type stored for `Window::on_close`.
+pub type CloseHandler = dyn Fn(WindowRef) + Send + Sync + 'static;
+
+static CLOSE_HANDLERS: LazyLock<Mutex<HashMap<usize, Vec<Arc<CloseHandler>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// How a window was last shown, to show it again on `ExitPolicy::ReopenOnClose`
+#[derive(Debug, Clone)]
+enum Shown {
+    Default(String),
+    Browser(String, Browser),
+    WebView(String),
+}
+
+static LAST_SHOWN: LazyLock<Mutex<HashMap<usize, Shown>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Windows closed through `close`, which are not reopened
+static CLOSE_REQUESTED: LazyLock<Mutex<HashSet<usize>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+static CLOSE_MONITOR: Once = Once::new();
+
+// How often the close monitor checks the shown windows
+const CLOSE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Owns a webui window, which is destroyed when the `Window` is dropped.
+/// Dereferences to `WindowRef` for everything else.
+#[derive(Debug)]
 pub struct Window {
+    inner: WindowRef,
+}
+
+/// Non-owning handle to a webui window, e.g. one built from `Event::window`.
+/// Copying or dropping it never closes nor destroys the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowRef {
     pub id: usize,
 }
 
+/// Keeps a handler bound. Dropping the handle, or calling `unbind`,
+/// unregisters the handler; `forget` keeps it for the window's lifetime.
+#[must_use = "dropping a BindHandle unbinds the handler, call `forget` to keep it bound"]
+pub struct BindHandle {
+    window_id: usize,
+    element: String,
+    bind_id: usize,
+    token: u64,
+}
+
+impl BindHandle {
+    pub fn bind_id(&self) -> usize {
+        self.bind_id
+    }
+
+    pub fn element(&self) -> &str {
+        &self.element
+    }
+
+    pub fn unbind(self) {
+        // Unregistered by `Drop`
+    }
+
+    pub fn forget(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for BindHandle {
+    fn drop(&mut self) {
+        BIND_STORE.remove_function(self.window_id, &self.element, Some(self.token));
+    }
+}
+
 impl Window {
     pub fn new() -> Window {
         let id = new_window();
-        Window { id }
+        Window {
+            inner: WindowRef { id },
+        }
     }
 
-    pub fn from_id(id: usize) -> Window {
-        Window { id }
+    // Create the window with a specific id, see `webui::get_new_window_id`
+    pub fn new_with_id(id: usize) -> Window {
+        new_window_id(id);
+        Window {
+            inner: WindowRef { id },
+        }
+    }
+
+    pub fn builder() -> WindowBuilder {
+        WindowBuilder::new()
+    }
+
+    pub fn to_ref(&self) -> WindowRef {
+        self.inner
+    }
+
+    // Close the window and free its resources, including the Rust handlers
+    pub fn destroy(self) {
+        // Destroyed by `Drop`
+    }
+
+    // Give up ownership, the window then lives until `webui::clean`
+    pub fn detach(self) -> WindowRef {
+        let inner = self.inner;
+        std::mem::forget(self);
+        inner
+    }
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Window::new()
+    }
+}
+
+impl std::ops::Deref for Window {
+    type Target = WindowRef;
+
+    fn deref(&self) -> &WindowRef {
+        &self.inner
+    }
+}
+
+impl WindowRef {
+    // Refer to an existing window without owning it
+    pub fn from_id(id: usize) -> WindowRef {
+        WindowRef { id }
     }
 
-    pub fn bind(&self, element: &str, func: fn(Event)) -> usize {
+    pub fn bind<F>(&self, element: &str, func: F) -> Result<BindHandle, WebUiError>
+    where
+        F: Fn(Event) + Send + Sync + 'static,
+    {
         // Element String to i8/u8
-        let element_c_str = CString::new(element).unwrap();
+        let element_c_str = CString::new(element)?;
         let element_c_char: *const c_char = element_c_str.as_ptr() as *const c_char;
 
         // Bind
@@ -46,67 +199,330 @@ impl Window {
 
             let window_id = webui_interface_get_window_id(self.id);
 
+            let bind_id = webui_bind(self.id, element_c_char, f);
+
             // Add the Rust user function to the list
-            BIND_STORE.add_function(window_id, element, func);
+            let token = BIND_STORE.add_function(window_id, element, bind_id, Arc::new(func));
+
+            Ok(BindHandle {
+                window_id,
+                element: element.to_owned(),
+                bind_id,
+                token,
+            })
+        }
+    }
+
+    // Bind a plain function, its arguments are decoded from the call and its
+    // return value is sent back. Decoding failures reject the caller's promise
+    // through the client shim, see `install_client_shim`.
+    pub fn bind_typed<H, Args>(&self, element: &str, handler: H) -> Result<BindHandle, WebUiError>
+    where
+        H: Handler<Args>,
+    {
+        self.install_client_shim();
+        self.bind(element, move |event| {
+            if let Err(err) = handler.call(&event) {
+                return_error(&event, "ARGUMENT", &err.to_string());
+            }
+        })
+    }
+
+    // Bind a handler returning a future, run on the executor set with
+    // `webui::set_executor` (a built-in thread pool by default). The call is
+    // answered at once so the dispatch thread is not held, and the client
+    // shim settles its `webui.call()` promise once the future completes.
+    pub fn bind_async<F, Fut>(&self, element: &str, handler: F) -> Result<BindHandle, WebUiError>
+    where
+        F: Fn(&Event) -> Fut + Send + Sync + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: IntoEventReturn + Send + 'static,
+    {
+        self.install_client_shim();
+        self.bind(element, move |event| {
+            let future = CatchPanic::new(handler(&event), event.win, &event.element);
+            let call = PendingCall::new(&event);
+            let _ = event.return_string(&call.pending_response());
+
+            executor().spawn(Box::pin(async move {
+                match future.await {
+                    Ok(output) => call.resolve(output),
+                    Err(panic) => call.reject("PANIC", &panic.to_string()),
+                }
+            }));
+        })
+    }
+
+    // Bind a function taking the JSON sent as first argument of the call,
+    // its return value is sent back as JSON
+    #[cfg(feature = "serde")]
+    pub fn bind_json<Req, Resp, F>(
+        &self,
+        element: &str,
+        handler: F,
+    ) -> Result<BindHandle, WebUiError>
+    where
+        Req: serde::de::DeserializeOwned,
+        Resp: serde::Serialize,
+        F: Fn(Req) -> Resp + Send + Sync + 'static,
+    {
+        self.install_client_shim();
+        self.bind(element, move |event| {
+            let request = match serde_json::from_str(&event.get_string()) {
+                Ok(request) => request,
+                Err(err) => {
+                    return return_error(&event, "JSON", &format!("invalid JSON argument: {}", err))
+                }
+            };
+
+            match serde_json::to_string(&handler(request)) {
+                Ok(response) => response.into_event_return(&event),
+                Err(err) => return_error(
+                    &event,
+                    "JSON",
+                    &format!("cannot serialize response: {}", err),
+                ),
+            }
+        })
+    }
+
+    // Serve `CLIENT_SHIM` with the HTML given to `show`, after its `webui.js`
+    // script, so that failed calls reject their promise. Pages loaded from a
+    // file or a URL must include it themselves. Done by the typed bindings,
+    // which should be bound before `show`.
+    pub fn install_client_shim(&self) {
+        let window_id = interface_get_window_id(self.id);
+        if SHIM_INSTALLED.lock().unwrap().insert(window_id) && self.is_shown() {
+            let _ = self.run(CLIENT_SHIM);
+        }
+    }
 
-            webui_bind(self.id, element_c_char, f)
+    // The content to show, with the client shim if the window needs it
+    fn page(&self, content: &str) -> String {
+        let window_id = interface_get_window_id(self.id);
+        let shim = SHIM_INSTALLED.lock().unwrap().contains(&window_id);
+        match shim.then(|| with_client_shim(content)).flatten() {
+            Some(page) => page,
+            None => content.to_owned(),
         }
     }
 
+    pub fn on_connect<F>(&self, func: F)
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.add_lifecycle_handler(EventType::WEBUI_EVENT_CONNECTED, Arc::new(func));
+    }
+
+    pub fn on_disconnect<F>(&self, func: F)
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.add_lifecycle_handler(EventType::WEBUI_EVENT_DISCONNECTED, Arc::new(func));
+    }
+
+    // Called with the target URL and the navigating client
+    pub fn on_navigation<F>(&self, func: F)
+    where
+        F: Fn(String, &Event) + Send + Sync + 'static,
+    {
+        self.add_lifecycle_handler(
+            EventType::WEBUI_EVENT_NAVIGATION,
+            Arc::new(move |event: &Event| func(event.get_string(), event)),
+        );
+    }
+
+    // Called with the id of the clicked element, empty if it has none
+    pub fn on_any_click<F>(&self, func: F)
+    where
+        F: Fn(&str, &Event) + Send + Sync + 'static,
+    {
+        self.add_lifecycle_handler(
+            EventType::WEBUI_EVENT_MOUSE_CLICK,
+            Arc::new(move |event: &Event| func(&event.element, event)),
+        );
+    }
+
+    // Called once the browser window is closed, by the user or by `close`.
+    // Closing is detected by polling, so it is reported with a short delay.
+    pub fn on_close<F>(&self, func: F)
+    where
+        F: Fn(WindowRef) + Send + Sync + 'static,
+    {
+        CLOSE_HANDLERS
+            .lock()
+            .unwrap()
+            .entry(self.id)
+            .or_default()
+            .push(Arc::new(func));
+        start_close_monitor();
+    }
+
+    // The connected clients, empty unless `track_clients` was called
+    pub fn clients(&self) -> Vec<Client> {
+        client::clients_of(self.id)
+    }
+
+    // Run the function body `js` on every connected client in parallel, with
+    // a buffer sized to each result, keyed by `Client::id`. Needs
+    // `Config::multi_client`, which tracks the clients of every window; a
+    // window whose clients were not tracked starts here, so it knows none yet.
+    pub fn script_all(
+        &self,
+        js: &str,
+        timeout: usize,
+    ) -> HashMap<ClientId, Result<String, ScriptError>> {
+        self.track_clients();
+        let clients = self.clients();
+
+        thread::scope(|scope| {
+            let runs: Vec<_> = clients
+                .iter()
+                .map(|client| {
+                    scope.spawn(move || {
+                        crate::script::script_body(
+                            |script, buffer_length| client.script(script, timeout, buffer_length),
+                            js,
+                        )
+                    })
+                })
+                .collect();
+
+            clients
+                .iter()
+                .zip(runs)
+                .map(|(client, run)| {
+                    let result = match run.join() {
+                        Ok(result) => result.map_err(|err| match err {
+                            WebUiError::ScriptError(err) => err,
+                            err => ScriptError::new(ScriptErrorKind::Internal, &err.to_string()),
+                        }),
+                        Err(_) => Err(ScriptError::new(
+                            ScriptErrorKind::Internal,
+                            "script worker panicked",
+                        )),
+                    };
+                    (client.id(), result)
+                })
+                .collect()
+        })
+    }
+
+    // Track the clients of this window for `Client::is_connected`. Clients
+    // are seen when they connect or call a binding, so call it before `show`.
+    // Connections are reported to the empty element binding, which this adds.
+    // Done for every window once `Config::multi_client` is enabled.
+    pub fn track_clients(&self) {
+        if !client::track(self.id) {
+            return;
+        }
+
+        self.on_connect(|event| client::connected(event_client(event)));
+        self.on_disconnect(|event| client::disconnected(event_client(event)));
+    }
+
+    // WebUI only reports lifecycle events to the empty element binding
+    fn add_lifecycle_handler(&self, event_type: EventType, func: Arc<LifecycleHandler>) {
+        let window_id = interface_get_window_id(self.id);
+        LIFECYCLE
+            .lock()
+            .unwrap()
+            .entry(window_id)
+            .or_insert_with(|| Lifecycle {
+                bind_id: unsafe { webui_bind(self.id, c"".as_ptr(), Some(bind_events_handler)) },
+                handlers: Vec::new(),
+            })
+            .handlers
+            .push((event_type, func));
+    }
+
+    // WebUI has no unbind, so the element stays bound in C and its events
+    // are simply ignored once the Rust handler is gone
+    pub fn unbind(&self, element: &str) -> bool {
+        let window_id = interface_get_window_id(self.id);
+        BIND_STORE.remove_function(window_id, element, None)
+    }
+
+    pub fn bindings(&self) -> Vec<String> {
+        let window_id = interface_get_window_id(self.id);
+        BIND_STORE.elements(window_id)
+    }
+
     pub fn get_best_browser(&self) -> Browser {
-        unsafe {
-            match webui_get_best_browser(self.id) {
-                0 => Browser::NoBrowser,
-                1 => Browser::AnyBrowser,
-                2 => Browser::Chrome,
-                3 => Browser::Firefox,
-                4 => Browser::Edge,
-                5 => Browser::Safari,
-                6 => Browser::Chromium,
-                7 => Browser::Opera,
-                8 => Browser::Brave,
-                9 => Browser::Vivaldi,
-                10 => Browser::Epic,
-                11 => Browser::Yandex,
-                12 => Browser::ChromiumBased,
-                13 => Browser::Webview,
-                _ => Browser::NoBrowser,
+        unsafe { Browser::try_from(webui_get_best_browser(self.id)).unwrap_or(Browser::NoBrowser) }
+    }
+
+    // Try each browser in order, returning the one that opened the window
+    pub fn show_with_fallback(
+        &self,
+        content: &str,
+        browsers: &[Browser],
+    ) -> Result<Browser, WebUiError> {
+        for &browser in browsers {
+            if self.show_browser(content, browser).is_ok() {
+                return Ok(browser);
             }
         }
+        Err(WebUiError::ShowFailed)
     }
 
-    pub fn show(&self, content: &str) -> bool {
+    pub fn show(&self, content: &str) -> Result<(), WebUiError> {
         unsafe {
             // Content String to i8/u8
-            let content_c_str = CString::new(content).unwrap();
+            let content_c_str = CString::new(self.page(content))?;
             let content_c_char: *const c_char = content_c_str.as_ptr() as *const c_char;
 
-            webui_show(self.id, content_c_char)
+            match webui_show(self.id, content_c_char) {
+                true => {
+                    self.shown(Shown::Default(content.to_owned()));
+                    Ok(())
+                }
+                false => Err(WebUiError::ShowFailed),
+            }
         }
     }
 
-    pub fn show_browser(&self, content: &str, browser: Browser) -> bool {
-        let content_c_str = CString::new(content).unwrap();
+    pub fn show_browser(&self, content: &str, browser: Browser) -> Result<(), WebUiError> {
+        let content_c_str = CString::new(self.page(content))?;
         let content_c_char: *const c_char = content_c_str.as_ptr() as *const c_char;
 
-        unsafe { webui_show_browser(self.id, content_c_char, browser as usize) }
+        unsafe {
+            match webui_show_browser(self.id, content_c_char, browser as usize) {
+                true => {
+                    self.shown(Shown::Browser(content.to_owned(), browser));
+                    Ok(())
+                }
+                false => Err(WebUiError::ShowFailed),
+            }
+        }
     }
 
-    pub fn start_server(&self, content: &str) -> String {
-        let content_c_str = CString::new(content).unwrap();
+    pub fn start_server(&self, content: &str) -> Result<String, WebUiError> {
+        let content_c_str = CString::new(self.page(content))?;
         let content_c_char: *const c_char = content_c_str.as_ptr() as *const c_char;
 
         unsafe {
-            let server = webui_start_server(self.id, content_c_char);
-            char_to_string(server)
+            let server = char_to_string(webui_start_server(self.id, content_c_char));
+            match server.is_empty() {
+                true => Err(WebUiError::ServerFailed),
+                false => Ok(server),
+            }
         }
     }
 
-    pub fn show_wv(&self, content: &str) -> bool {
-        let content_c_str = CString::new(content).unwrap();
+    pub fn show_wv(&self, content: &str) -> Result<(), WebUiError> {
+        let content_c_str = CString::new(self.page(content))?;
         let content_c_char: *const c_char = content_c_str.as_ptr() as *const c_char;
 
-        unsafe { webui_show_wv(self.id, content_c_char) }
+        unsafe {
+            match webui_show_wv(self.id, content_c_char) {
+                true => {
+                    self.shown(Shown::WebView(content.to_owned()));
+                    Ok(())
+                }
+                false => Err(WebUiError::ShowFailed),
+            }
+        }
     }
 
     pub fn set_kiosk(&self, status: bool) {
@@ -121,24 +537,30 @@ impl Window {
         }
     }
 
-    pub fn close(self) {
+    // Close the browser window, it can be shown again
+    pub fn close(&self) {
+        CLOSE_REQUESTED.lock().unwrap().insert(self.id);
         unsafe {
             webui_close(self.id);
         }
     }
 
-    pub fn destroy(&self) {
-        unsafe {
-            webui_destroy(self.id);
-        }
+    // Remember a successful show, for the registry and the close monitor
+    fn shown(&self, shown: Shown) {
+        mark_window_shown(self.id);
+        LAST_SHOWN.lock().unwrap().insert(self.id, shown);
+        CLOSE_REQUESTED.lock().unwrap().remove(&self.id);
     }
 
-    pub fn set_root_folder(&self, folder: &str) {
-        let folder_c_str = CString::new(folder).unwrap();
+    pub fn set_root_folder(&self, folder: &str) -> Result<(), WebUiError> {
+        let folder_c_str = CString::new(folder)?;
         let folder_c_char: *const c_char = folder_c_str.as_ptr() as *const c_char;
 
         unsafe {
-            webui_set_root_folder(self.id, folder_c_char);
+            match webui_set_root_folder(self.id, folder_c_char) {
+                true => Ok(()),
+                false => Err(WebUiError::RootFolderRejected(folder.to_owned())),
+            }
         }
     }
 
@@ -155,26 +577,28 @@ impl Window {
         unsafe { webui_is_shown(self.id) }
     }
 
-    pub fn set_icon(&self, icon: &str, kind: &str) {
-        let icon_c_str = CString::new(icon).unwrap();
-        let kind_c_str = CString::new(kind).unwrap();
+    pub fn set_icon(&self, icon: &str, kind: &str) -> Result<(), WebUiError> {
+        let icon_c_str = CString::new(icon)?;
+        let kind_c_str = CString::new(kind)?;
         let icon_c_char: *const c_char = icon_c_str.as_ptr() as *const c_char;
         let kind_c_char: *const c_char = kind_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_set_icon(self.id, icon_c_char, kind_c_char);
         }
+        Ok(())
     }
 
-    pub fn send_raw(&self, function: &str, data: &[u8]) {
+    pub fn send_raw(&self, function: &str, data: &[u8]) -> Result<(), WebUiError> {
         let size = data.len();
         let raw = data.as_ptr() as *mut std::os::raw::c_void;
-        let function_c_str = CString::new(function).unwrap();
+        let function_c_str = CString::new(function)?;
         let function_c_char: *const c_char = function_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_send_raw(self.id, function_c_char, raw, size);
         }
+        Ok(())
     }
 
     pub fn set_hide(&self, status: bool) {
@@ -195,30 +619,35 @@ impl Window {
         }
     }
 
-    pub fn set_profile(&self, name: &str, path: &str) {
-        let name_c_str = CString::new(name).unwrap();
-        let path_c_str = CString::new(path).unwrap();
+    pub fn set_profile(&self, name: &str, path: &str) -> Result<(), WebUiError> {
+        let name_c_str = CString::new(name)?;
+        let path_c_str = CString::new(path)?;
         let name_c_char: *const c_char = name_c_str.as_ptr() as *const c_char;
         let path_c_char: *const c_char = path_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_set_profile(self.id, name_c_char, path_c_char);
         }
+        Ok(())
     }
 
-    pub fn set_proxy(&self, proxy: &str) {
-        let proxy_c_str = CString::new(proxy).unwrap();
+    pub fn set_proxy(&self, proxy: &str) -> Result<(), WebUiError> {
+        let proxy_c_str = CString::new(proxy)?;
         let proxy_c_char: *const c_char = proxy_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_set_proxy(self.id, proxy_c_char);
         }
+        Ok(())
     }
 
-    pub fn get_url(&self) -> String {
+    pub fn get_url(&self) -> Result<String, WebUiError> {
         unsafe {
             let url = webui_get_url(self.id);
-            char_to_string(url)
+            match url.is_null() {
+                true => Err(WebUiError::WindowNotFound(self.id)),
+                false => Ok(char_to_string(url)),
+            }
         }
     }
 
@@ -228,13 +657,14 @@ impl Window {
         }
     }
 
-    pub fn navigate(&self, url: &str) {
-        let url_c_str = CString::new(url).unwrap();
+    pub fn navigate(&self, url: &str) -> Result<(), WebUiError> {
+        let url_c_str = CString::new(url)?;
         let url_c_char: *const c_char = url_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_navigate(self.id, url_c_char);
         }
+        Ok(())
     }
 
     pub fn delete_profile(&self) {
@@ -255,8 +685,13 @@ impl Window {
         unsafe { webui_get_port(self.id) }
     }
 
-    pub fn set_port(&self, port: usize) -> bool {
-        unsafe { webui_set_port(self.id, port) }
+    pub fn set_port(&self, port: usize) -> Result<(), WebUiError> {
+        unsafe {
+            match webui_set_port(self.id, port) {
+                true => Ok(()),
+                false => Err(WebUiError::PortUnavailable(port)),
+            }
+        }
     }
 
     pub fn set_event_blocking(&self, status: bool) {
@@ -265,33 +700,42 @@ impl Window {
         }
     }
 
-    pub fn run(&self, script: &str) {
-        let script_c_str = CString::new(script).unwrap();
+    pub fn run(&self, script: &str) -> Result<(), WebUiError> {
+        let script_c_str = CString::new(script)?;
         let script_c_char: *const c_char = script_c_str.as_ptr() as *const c_char;
 
         unsafe {
             webui_run(self.id, script_c_char);
         }
+        Ok(())
     }
 
-    pub fn script(&self, script: &str, timeout: usize, buffer_length: usize) -> Result<String, ()> {
-        let script_c_str = CString::new(script).unwrap();
+    // Run `script` and return its result. A result of `buffer_length - 1`
+    // bytes or more fills the buffer and is reported as `Truncated`.
+    pub fn script(
+        &self,
+        script: &str,
+        timeout: usize,
+        buffer_length: usize,
+    ) -> Result<String, WebUiError> {
+        if !self.is_shown() {
+            return Err(ScriptError::new(ScriptErrorKind::Disconnected, "").into());
+        }
+
+        let script_c_str = CString::new(crate::script::catch_exceptions(script))?;
         let script_c_char: *const c_char = script_c_str.as_ptr() as *const c_char;
 
-        let buffer_c_str = CString::new(vec![0; buffer_length]).unwrap();
-        let buffer_c_char: *mut c_char = buffer_c_str.as_ptr() as *mut c_char;
+        let mut buffer = vec![0u8; buffer_length];
 
         unsafe {
-            match webui_script(
+            let success = webui_script(
                 self.id,
                 script_c_char,
                 timeout,
-                buffer_c_char,
+                buffer.as_mut_ptr() as *mut c_char,
                 buffer_length,
-            ) {
-                true => Ok(char_to_string(buffer_c_char)),
-                false => Err(()),
-            }
+            );
+            script_result(success, buffer)
         }
     }
 
@@ -304,19 +748,203 @@ impl Window {
 
 impl Drop for Window {
     fn drop(&mut self) {
-        self.destroy();
+        unsafe {
+            // Free the Rust handlers bound to this window
+            let window_id = webui_interface_get_window_id(self.id);
+            BIND_STORE.remove_window(window_id);
+            LIFECYCLE.lock().unwrap().remove(&window_id);
+            SHIM_INSTALLED.lock().unwrap().remove(&window_id);
+            interface_unbind_all(window_id);
+
+            webui_destroy(self.id);
+        }
+        untrack_window(self.id);
+        CLOSE_HANDLERS.lock().unwrap().remove(&self.id);
+        LAST_SHOWN.lock().unwrap().remove(&self.id);
+        CLOSE_REQUESTED.lock().unwrap().remove(&self.id);
+        client::forget_window(self.id);
     }
 }
 
 unsafe extern "C" fn bind_events_handler(event: *mut webui_event_t) {
-    let evt = Event::new(event);
-
-    // Call the Rust user function
-    unsafe {
-        let window_id = webui_interface_get_window_id((*event).window);
+    let evt = unsafe { Event::from_raw(event) };
+    let (window, element) = (evt.win, evt.element.clone());
+
+    // Call the Rust user function, a panic must not unwind into webui
+    let result = catch_panic(window, &element, || unsafe {
+        let window_id = webui_interface_get_window_id(window);
+
+        if evt.event_type != EventType::WEBUI_EVENT_CALLBACK {
+            dispatch_lifecycle(window_id, &evt);
+        } else if client::is_tracked(evt.win) {
+            // A client connected before the tracking started
+            client::connected(event_client(&evt));
+        }
 
-        if let Some(func) = BIND_STORE.get_function(window_id, &evt.element) {
+        if let Some(func) = BIND_STORE.get_function(window_id, &evt.element, evt.bind_id) {
             func(evt);
         }
+    });
+
+    if let Err(panic) = result {
+        return_error(
+            &unsafe { Event::from_raw(event) },
+            "PANIC",
+            &panic.to_string(),
+        );
+    }
+}
+
+fn event_client(event: &Event) -> Client {
+    Client::new(event.win, event.client_id, event.connection_id)
+}
+
+fn dispatch_lifecycle(window_id: usize, evt: &Event) {
+    // Only the empty element binding reports lifecycle events, the element
+    // binding webui also calls for the same event must not repeat them.
+    // Collect first, so handlers can register more handlers.
+    let handlers: Vec<Arc<LifecycleHandler>> = match LIFECYCLE.lock().unwrap().get(&window_id) {
+        Some(lifecycle) if lifecycle.bind_id == evt.bind_id => lifecycle
+            .handlers
+            .iter()
+            .filter(|(event_type, _)| *event_type == evt.event_type)
+            .map(|(_, func)| func.clone())
+            .collect(),
+        _ => return,
+    };
+
+    for func in handlers {
+        func(evt);
+    }
+}
+
+// Watch the shown windows from a background thread, started on first use
+pub(crate) fn start_close_monitor() {
+    CLOSE_MONITOR.call_once(|| {
+        thread::Builder::new()
+            .name("webui-rs-close-monitor".to_owned())
+            .spawn(|| {
+                let mut open = HashSet::new();
+                loop {
+                    check_closed(&mut open);
+                    thread::sleep(CLOSE_POLL_INTERVAL);
+                }
+            })
+            .expect("failed to spawn webui-rs close monitor thread");
+    });
+}
+
+// Report the windows in `open` that are no longer shown, then record the
+// windows shown since the last check
+fn check_closed(open: &mut HashSet<usize>) {
+    let windows: Vec<(usize, Shown)> = LAST_SHOWN
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(&id, shown)| (id, shown.clone()))
+        .collect();
+    open.retain(|id| windows.iter().any(|(window, _)| window == id));
+
+    for (id, shown) in windows {
+        let win = WindowRef::from_id(id);
+        let is_shown = win.is_shown();
+
+        if is_shown {
+            open.insert(id);
+            continue;
+        }
+        if !open.remove(&id) {
+            continue;
+        }
+
+        let handlers = CLOSE_HANDLERS
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .unwrap_or_default();
+        for func in handlers {
+            let _ = catch_panic(id, "", || func(win));
+        }
+
+        let requested = CLOSE_REQUESTED.lock().unwrap().remove(&id);
+        if !requested && !exit_requested() && exit_policy() == ExitPolicy::ReopenOnClose {
+            let _ = match shown {
+                Shown::Default(content) => win.show(&content),
+                Shown::Browser(content, browser) => win.show_browser(&content, browser),
+                Shown::WebView(content) => win.show_wv(&content),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Send an event the way webui does, to the binding `bind_id`
+    fn fire(window: usize, element: &str, bind_id: usize, event_type: EventType) {
+        let element = CString::new(element).unwrap();
+        let mut event = webui_event_t {
+            window,
+            event_type: event_type as usize,
+            element: element.as_ptr() as *mut c_char,
+            event_number: 0,
+            bind_id,
+            client_id: 0,
+            connection_id: 0,
+            cookies: std::ptr::null_mut(),
+        };
+        unsafe { bind_events_handler(&mut event) };
+    }
+
+    #[test]
+    fn test_handlers_run_once_next_to_the_empty_element_binding() {
+        static TYPED: AtomicUsize = AtomicUsize::new(0);
+        static OTHER: AtomicUsize = AtomicUsize::new(0);
+        static CONNECTED: AtomicUsize = AtomicUsize::new(0);
+
+        // Below `WEBUI_MAX_IDS`, and never created so no test shares it
+        let window = 200;
+        let window_id = interface_get_window_id(window);
+        let (all, typed, other) = (7000, 7001, 7002);
+
+        // Stand for a typed binding and a plain one, registered the way
+        // `bind` does. They do not read the event, which would call webui.
+        let func = Arc::new(|_: Event| {
+            TYPED.fetch_add(1, Ordering::SeqCst);
+        });
+        BIND_STORE.add_function(window_id, "typed", typed, func);
+        let func = Arc::new(|_: Event| {
+            OTHER.fetch_add(1, Ordering::SeqCst);
+        });
+        BIND_STORE.add_function(window_id, "other", other, func);
+        LIFECYCLE.lock().unwrap().insert(
+            window_id,
+            Lifecycle {
+                bind_id: all,
+                handlers: vec![(
+                    EventType::WEBUI_EVENT_CONNECTED,
+                    Arc::new(|_: &Event| {
+                        CONNECTED.fetch_add(1, Ordering::SeqCst);
+                    }),
+                )],
+            },
+        );
+
+        // webui calls both the element binding and the empty element one
+        for (element, bind_id) in [("typed", typed), ("other", other)] {
+            fire(window, element, bind_id, EventType::WEBUI_EVENT_CALLBACK);
+            fire(window, element, all, EventType::WEBUI_EVENT_CALLBACK);
+        }
+        fire(window, "", all, EventType::WEBUI_EVENT_CONNECTED);
+
+        assert_eq!(TYPED.load(Ordering::SeqCst), 1);
+        assert_eq!(OTHER.load(Ordering::SeqCst), 1);
+        assert_eq!(CONNECTED.load(Ordering::SeqCst), 1);
+
+        BIND_STORE.remove_window(window_id);
+        LIFECYCLE.lock().unwrap().remove(&window_id);
     }
 }
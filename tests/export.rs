@@ -0,0 +1,39 @@
+use webui_rs::export::Param;
+use webui_rs::webui;
+
+#[webui::export]
+fn add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+#[webui::export(name = "lookup")]
+fn find_user(id: usize, nickname: Option<String>) -> Result<String, String> {
+    nickname.ok_or_else(|| format!("no user {}", id))
+}
+
+#[test]
+fn test_export_metadata() {
+    let add = webui::exports().find(|e| e.name == "add").unwrap();
+    assert_eq!(
+        add.params,
+        &[
+            Param {
+                name: "a",
+                ty: "i64"
+            },
+            Param {
+                name: "b",
+                ty: "i64"
+            }
+        ]
+    );
+    assert_eq!(add.returns, "i64");
+
+    let lookup = webui::exports().find(|e| e.name == "lookup").unwrap();
+    assert_eq!(lookup.params[1].ty, "Option<String>");
+    assert_eq!(lookup.returns, "Result<String, String>");
+    assert_eq!(
+        lookup.js_stub(),
+        "function lookup(id, nickname) { return webui.call(\"lookup\", id, nickname); }"
+    );
+}
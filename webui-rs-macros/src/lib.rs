@@ -0,0 +1,117 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Error, FnArg, ItemFn, LitStr, Pat, ReturnType, Token};
+
+// `#[export]` or `#[export(name = "jsName")]`
+struct ExportArgs {
+    name: Option<LitStr>,
+}
+
+impl Parse for ExportArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(ExportArgs { name: None });
+        }
+
+        let key: syn::Ident = input.parse()?;
+        if key != "name" {
+            return Err(Error::new(key.span(), "expected `name = \"...\"`"));
+        }
+        input.parse::<Token![=]>()?;
+        let name = input.parse()?;
+        Ok(ExportArgs { name: Some(name) })
+    }
+}
+
+/// Exports a function to JavaScript.
+///
+/// Registers the function so that `webui::register_all(&win)` binds it with
+/// `Window::bind_typed`, and records its parameter list in `webui::exports()`.
+#[proc_macro_attribute]
+pub fn export(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as ExportArgs);
+    let func = parse_macro_input!(item as ItemFn);
+
+    match expand(args, func) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(args: ExportArgs, func: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &func.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(
+            asyncness,
+            "exported functions cannot be async",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "exported functions cannot be generic",
+        ));
+    }
+    if sig.inputs.len() > 8 {
+        return Err(Error::new_spanned(
+            &sig.inputs,
+            "exported functions take at most 8 arguments",
+        ));
+    }
+
+    let ident = &sig.ident;
+    let name = args
+        .name
+        .unwrap_or_else(|| LitStr::new(&ident.to_string(), Span::call_site()));
+
+    let mut params = Vec::new();
+    for input in &sig.inputs {
+        let FnArg::Typed(arg) = input else {
+            return Err(Error::new_spanned(
+                input,
+                "exported functions cannot take `self`",
+            ));
+        };
+        let param = match &*arg.pat {
+            Pat::Ident(pat) => pat.ident.to_string(),
+            Pat::Wild(_) => "_".to_owned(),
+            pat => return Err(Error::new_spanned(pat, "expected an argument name")),
+        };
+        let ty = type_name(&arg.ty);
+        params.push(quote! {
+            ::webui_rs::export::Param { name: #param, ty: #ty }
+        });
+    }
+
+    let returns = match &sig.output {
+        ReturnType::Default => "()".to_owned(),
+        ReturnType::Type(_, ty) => type_name(ty),
+    };
+
+    Ok(quote! {
+        #func
+
+        ::webui_rs::export::inventory::submit! {
+            ::webui_rs::export::Export {
+                name: #name,
+                params: &[#(#params),*],
+                returns: #returns,
+                register: |win| win.bind_typed(#name, #ident),
+            }
+        }
+    })
+}
+
+// Type as written in the source, without the spaces added by `quote`
+fn type_name(ty: &impl ToTokens) -> String {
+    ty.to_token_stream()
+        .to_string()
+        .replace(" < ", "<")
+        .replace("< ", "<")
+        .replace(" <", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace("& ", "&")
+}